@@ -0,0 +1,93 @@
+// Small key/value cache kept in defaults storage. Entries are stored as "<unix time>\n<value>";
+// an index of written keys (defaults can't be enumerated) drives eviction and clearing.
+// Every cached value (responses, reader file lists, redirects, validators) goes through this
+// module, so the indexes stay bounded and "Clear Cached Data" reaches all of them.
+use aidoku::{
+    alloc::{String, Vec, string::ToString},
+    imports::{defaults::{defaults_get, defaults_set, DefaultValue}, std::current_date},
+};
+
+const INDEX_KEY: &str = "cache.keys";
+// Oldest entries are evicted past this many keys
+const MAX_ENTRIES: usize = 64;
+// Larger values (e.g. huge series indexes) aren't worth the storage
+const MAX_VALUE_LEN: usize = 256 * 1024;
+// Oldest entries are also evicted once the cached values together pass this size
+const MAX_TOTAL_BYTES: usize = 2 * 1024 * 1024;
+// Validators (ETag / Last-Modified) are a few bytes each and wanted for every series in a library,
+// so they get their own, larger index instead of being evicted by response bodies
+const VALIDATOR_INDEX_KEY: &str = "cache.validators";
+const MAX_VALIDATORS: usize = 1024;
+
+// Value stored under `key`, if it is younger than `ttl` seconds
+pub fn get(key: &str, ttl: i64) -> Option<String> {
+    let raw = defaults_get::<String>(key)?;
+    let (stored_at, value) = raw.split_once('\n')?;
+    let stored_at = stored_at.parse::<i64>().ok()?;
+    if current_date() - stored_at > ttl { return None; }
+    Some(value.to_string())
+}
+
+pub fn set(key: &str, value: &str) {
+    if value.len() > MAX_VALUE_LEN { return; }
+    store(INDEX_KEY, MAX_ENTRIES, MAX_TOTAL_BYTES, key, value);
+}
+
+// Like set, for small bookkeeping values kept in the validator index; read them back with get
+pub fn set_validator(key: &str, value: &str) {
+    store(VALIDATOR_INDEX_KEY, MAX_VALIDATORS, usize::MAX, key, value);
+}
+
+// Write the entry and evict the oldest ones past either limit; the new entry itself always stays
+fn store(index_key: &str, max_entries: usize, max_bytes: usize, key: &str, value: &str) {
+    let mut raw = current_date().to_string();
+    raw.push('\n');
+    raw.push_str(value);
+    let len = raw.len();
+    defaults_set(key, DefaultValue::String(raw));
+
+    let mut entries = index(index_key);
+    entries.retain(|(k, _)| k != key);
+    entries.push((key.to_string(), len));
+    let mut total: usize = entries.iter().map(|(_, len)| len).sum();
+    let mut evicted = 0;
+    while evicted + 1 < entries.len() && (entries.len() - evicted > max_entries || total > max_bytes) {
+        total -= entries[evicted].1;
+        evicted += 1;
+    }
+    for (old, _) in entries.drain(..evicted) {
+        defaults_set(&old, DefaultValue::Null);
+    }
+    // One "<size> <key>" line per entry, oldest first
+    let mut lines = String::new();
+    for (key, len) in &entries {
+        if !lines.is_empty() { lines.push('\n'); }
+        lines.push_str(&len.to_string());
+        lines.push(' ');
+        lines.push_str(key);
+    }
+    defaults_set(index_key, DefaultValue::String(lines));
+}
+
+// Wipe every cached entry, then the indexes themselves
+pub fn clear() {
+    for index_key in [INDEX_KEY, VALIDATOR_INDEX_KEY] {
+        for (key, _) in index(index_key) {
+            defaults_set(&key, DefaultValue::Null);
+        }
+        defaults_set(index_key, DefaultValue::Null);
+    }
+}
+
+// Indexed keys with their stored sizes. Cache keys never start with a number, so a line without
+// a size prefix (written before sizes were tracked) is a bare key counted as empty.
+fn index(index_key: &str) -> Vec<(String, usize)> {
+    defaults_get::<String>(index_key)
+        .map(|raw| raw.split('\n').filter(|line| !line.is_empty()).map(|line| {
+            match line.split_once(' ').and_then(|(len, key)| Some((key, len.parse::<usize>().ok()?))) {
+                Some((key, len)) => (key.to_string(), len),
+                None => (line.to_string(), 0),
+            }
+        }).collect())
+        .unwrap_or_default()
+}
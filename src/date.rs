@@ -0,0 +1,171 @@
+// Upload dates from the index table: relative ("5 mins ago", "a day ago") or absolute
+// ("2021-03-04 18:22") in the server's timezone. All results are unix seconds.
+use aidoku::{alloc::Vec, imports::std::current_date};
+
+// Placeholder dates on old archives ("1901-12-13", "1970-01-01") predate any scan on the site
+const EARLIEST_PLAUSIBLE: i64 = 631_152_000; // 1990-01-01
+// Slack for server clocks running ahead of the device
+const FUTURE_SLACK: i64 = 86400;
+
+// Unix time of an index-table date, or None when it can't be read or is nonsensical.
+// `utc_offset` is the server's offset in seconds east of UTC and only applies to absolute dates.
+pub fn parse_chapter_date(raw: &str, utc_offset: i64) -> Option<i64> {
+    let raw = raw.trim();
+    if raw.is_empty() { return None; }
+    let date = if raw.ends_with("ago") {
+        let parts: Vec<&str> = raw.split(' ').collect();
+        // "a day ago" / "an hour ago" mean one unit
+        let amount = match parts.first() { Some(&"a") | Some(&"an") => Some(1), Some(n) => n.parse::<i64>().ok(), None => None };
+        relative_date(parts.get(1)?, amount?)?
+    } else {
+        parse_absolute(raw)?.saturating_sub(utc_offset)
+    };
+    Some(date).filter(|d| is_plausible(*d))
+}
+
+// Rejects placeholder, pre-1990 and future timestamps
+fn is_plausible(date: i64) -> bool {
+    date >= EARLIEST_PLAUSIBLE && date <= current_date().saturating_add(FUTURE_SLACK)
+}
+
+// Anchor "N units ago" to the runtime's current time; None for units it doesn't know
+fn relative_date(unit: &str, amount: i64) -> Option<i64> {
+    // Months and years use 30/365-day approximations, matching the site's coarse rounding
+    let unit_secs = if unit.starts_with("min") { 60 }
+        else if unit.starts_with("hour") { 3600 }
+        else if unit.starts_with("sec") { 1 }
+        else if unit.starts_with("day") { 86400 }
+        else if unit.starts_with("week") { 7 * 86400 }
+        else if unit.starts_with("month") { 30 * 86400 }
+        else if unit.starts_with("year") { 365 * 86400 }
+        else { return None };
+    // Saturating: a garbled "99999999999 years ago" must not overflow
+    Some(current_date().saturating_sub(amount.saturating_mul(unit_secs)))
+}
+
+// "yyyy-MM-dd", "yyyy-MM-dd HH:mm" or "yyyy-MM-dd HH:mm:ss" as seconds since the epoch, read as UTC.
+// New layouts only need to produce the same fields here.
+fn parse_absolute(raw: &str) -> Option<i64> {
+    let (date, time) = raw.split_once([' ', 'T']).unwrap_or((raw, ""));
+    let mut ymd = date.splitn(3, '-').map(|p| p.parse::<i32>().ok());
+    let (year, month, day) = (ymd.next()??, ymd.next()??, ymd.next()??);
+    if !(1..=12).contains(&month) || day < 1 || day > days_in_month(year, month) { return None; }
+    let mut hms = time.split(':').filter(|p| !p.is_empty()).map(|p| p.parse::<i64>().ok());
+    let hour = hms.next().map_or(Some(0), |h| h)?;
+    let minute = hms.next().map_or(Some(0), |m| m)?;
+    let second = hms.next().map_or(Some(0), |s| s)?;
+    if !(0..=23).contains(&hour) || !(0..=59).contains(&minute) || !(0..=59).contains(&second) { return None; }
+    Some(days_since_epoch(year, month, day) * 86400 + hour * 3600 + minute * 60 + second)
+}
+
+// "+5", "-3:30", "5:45" -> seconds east of UTC
+pub fn parse_utc_offset(raw: &str) -> Option<i64> {
+    let raw = raw.trim();
+    let (sign, rest) = match raw.strip_prefix('-') {
+        Some(rest) => (-1, rest),
+        None => (1, raw.strip_prefix('+').unwrap_or(raw)),
+    };
+    let (hours, minutes) = rest.split_once(':').unwrap_or((rest, "0"));
+    let hours = hours.parse::<i64>().ok().filter(|h| *h <= 14)?;
+    let minutes = minutes.parse::<i64>().ok().filter(|m| *m < 60)?;
+    Some(sign * (hours * 3600 + minutes * 60))
+}
+
+fn is_leap_year(y: i32) -> bool {
+    (y % 4 == 0 && y % 100 != 0) || y % 400 == 0
+}
+
+fn days_in_month(y: i32, m: i32) -> i32 {
+    match m {
+        2 if is_leap_year(y) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+// Exact proleptic Gregorian day count (Howard Hinnant's days_from_civil): March-based years put
+// the leap day at the end, so no month table is needed. Works in i64 so any i32 year fits.
+fn days_since_epoch(y: i32, m: i32, d: i32) -> i64 {
+    let (y, m, d) = (y as i64 - (m <= 2) as i64, m as i64, d as i64);
+    let era = (if y >= 0 { y } else { y - 399 }) / 400;
+    let yoe = y - era * 400;
+    let doy = (153 * (m + (if m > 2 { -3 } else { 9 })) + 2) / 5 + d - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146097 + doe - 719468 // days since 1970-01-01
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use aidoku_test::aidoku_test;
+
+    #[aidoku_test]
+    fn days_since_epoch_across_boundaries() {
+        assert_eq!(days_since_epoch(1970, 1, 1), 0);
+        assert_eq!(days_since_epoch(1969, 12, 31), -1);
+        // Year end
+        assert_eq!(days_since_epoch(1999, 12, 31), 10956);
+        assert_eq!(days_since_epoch(2000, 1, 1), 10957);
+        assert_eq!(days_since_epoch(2024, 12, 31), 20088);
+        assert_eq!(days_since_epoch(2025, 1, 1), 20089);
+        // Leap day of a 400-year leap year, then the day after
+        assert_eq!(days_since_epoch(2000, 2, 29), 11016);
+        assert_eq!(days_since_epoch(2000, 3, 1), 11017);
+        // Century years that aren't leap years
+        assert_eq!(days_since_epoch(1900, 3, 1), -25508);
+        assert_eq!(days_since_epoch(2100, 3, 1), 47541);
+    }
+
+    #[aidoku_test]
+    fn absolute_dates() {
+        assert_eq!(parse_absolute("2021-03-04"), Some(1614816000));
+        assert_eq!(parse_absolute("2021-03-04 18:22"), Some(1614882120));
+        assert_eq!(parse_absolute("2021-03-04T18:22:05"), Some(1614882125));
+        assert_eq!(parse_absolute("2021-12-31 23:59:59"), Some(1640995199));
+        assert_eq!(parse_absolute("2020-02-29"), Some(1582934400));
+    }
+
+    #[aidoku_test]
+    fn absolute_dates_reject_impossible_days() {
+        assert_eq!(parse_absolute("2021-02-29"), None);
+        assert_eq!(parse_absolute("1900-02-29"), None);
+        assert!(parse_absolute("2000-02-29").is_some());
+        assert_eq!(parse_absolute("2021-04-31"), None);
+        assert_eq!(parse_absolute("2021-13-01"), None);
+        assert_eq!(parse_absolute("2021-00-10"), None);
+        assert_eq!(parse_absolute("2021-01-00"), None);
+        assert_eq!(parse_absolute("2021-12-31 24:00"), None);
+        assert_eq!(parse_absolute("2021-12-31 23:60"), None);
+    }
+
+    #[aidoku_test]
+    fn utc_offsets() {
+        assert_eq!(parse_utc_offset("+5"), Some(18000));
+        assert_eq!(parse_utc_offset("-3:30"), Some(-12600));
+        assert_eq!(parse_utc_offset("5:45"), Some(20700));
+        assert_eq!(parse_utc_offset(" 0 "), Some(0));
+        assert_eq!(parse_utc_offset("+14"), Some(50400));
+        assert_eq!(parse_utc_offset("+15"), None);
+        assert_eq!(parse_utc_offset("5:60"), None);
+        assert_eq!(parse_utc_offset(""), None);
+        assert_eq!(parse_utc_offset("UTC"), None);
+    }
+
+    #[aidoku_test]
+    fn chapter_dates_reject_garbage() {
+        for raw in [
+            "", " ", "ago", "a ago", "years ago", "x days ago", "99999999999 years ago",
+            // Placeholders and future dates
+            "1901-12-13", "1970-01-01", "9999-12-31", "-5 days ago",
+            "-2021-03-04", "2021--03", "2021-03", "2021-03-04-05", "2021-03-04 18:22 UTC",
+            "2021-03-04 -1:00", "2021-03-04 18:-5", "２０２１-03-04", "2147483647-12-31", "-2147483648-01-01",
+        ] {
+            assert_eq!(parse_chapter_date(raw, 0), None, "{raw}");
+        }
+        // Offsets at either extreme don't overflow
+        assert_eq!(parse_chapter_date("2021-03-04", i64::MAX), None);
+        assert_eq!(parse_chapter_date("2021-03-04", i64::MIN), None);
+        assert_eq!(parse_chapter_date("2021-03-04 18:22", 3600), Some(1614878520));
+    }
+}
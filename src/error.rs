@@ -0,0 +1,98 @@
+// Failures surfaced to the user. Each carries the request it happened on; Display picks the
+// user-facing wording and, with "Detailed Errors" on, appends the phase, URL, status and any
+// selector that found nothing.
+use aidoku::{alloc::String, prelude::*, Result};
+use core::fmt;
+
+#[derive(Clone, Copy)]
+pub enum Phase {
+    Auth,
+    Network,
+    Parse,
+}
+
+// What the failed request was for; status codes read differently for each
+#[derive(Clone, Copy)]
+pub enum Target {
+    Page,
+    Reader,
+    Images,
+}
+
+pub enum MadokamiError {
+    // No response at all
+    Network { url: String, attempts: usize },
+    // The server answered with an error status
+    Status { url: String, status: i32, target: Target },
+    // The body ended mid-document; retrying usually gets the whole page
+    Truncated { url: String },
+    // A browser check or maintenance page stood in for the requested one
+    Challenge { url: String, status: i32 },
+    // The page loaded but lacked what the source parses
+    Parse { url: String, status: Option<i32>, missing: Option<&'static str>, reason: &'static str },
+    // A listing id this version doesn't provide (e.g. left over from a newer version's settings)
+    UnknownListing { id: String },
+}
+
+impl MadokamiError {
+    pub fn phase(&self) -> Phase {
+        match self {
+            Self::Network { .. } => Phase::Network,
+            Self::Status { status: 401 | 403, .. } => Phase::Auth,
+            Self::Status { .. } | Self::Challenge { .. } => Phase::Network,
+            Self::Truncated { .. } | Self::Parse { .. } | Self::UnknownListing { .. } => Phase::Parse,
+        }
+    }
+
+    fn message(&self) -> &'static str {
+        match self {
+            Self::Network { .. } => "Couldn't reach Madokami; check your connection and try again",
+            Self::Status { status, target, .. } => match (*target, *status) {
+                (Target::Images, 401) => "Madokami rejected the page images: check your username and password",
+                (Target::Images, 403) => "Your Madokami account isn't allowed to load images from this file",
+                (Target::Images, 404 | 410) => "Page images for this file are missing on Madokami; refresh the series",
+                (_, 401) => "Madokami requires login: set your username and password in the source settings",
+                (Target::Reader, 403) => "Your Madokami account doesn't have access to this file",
+                (Target::Reader, 404 | 410) => "This file was removed or renamed on Madokami; refresh the series",
+                (_, 403) => "Your Madokami account doesn't have access to this folder",
+                (_, 404 | 410) => "This folder was removed or renamed on Madokami",
+                (_, 500..=599) => "Madokami is having server trouble; try again later",
+                _ => "Madokami returned an unexpected error",
+            },
+            Self::Truncated { .. } => "Madokami sent an incomplete page; refresh to try again",
+            Self::Challenge { .. } => "Madokami is showing a browser check or maintenance page; open the site in WebView or a browser, then try again",
+            Self::Parse { reason, .. } => reason,
+            Self::UnknownListing { .. } => "This listing isn't available in this version of the source",
+        }
+    }
+
+    // Return the error from a source entry point
+    pub fn fail<T>(self) -> Result<T> {
+        bail!("{}", self)
+    }
+}
+
+impl fmt::Display for MadokamiError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.message())?;
+        if !crate::debug_enabled() { return Ok(()); }
+        let phase = match self.phase() {
+            Phase::Auth => "auth",
+            Phase::Network => "network",
+            Phase::Parse => "parse",
+        };
+        match self {
+            Self::Network { url, attempts } => write!(f, " [{phase}: GET {url} failed after {attempts} attempt(s)]"),
+            Self::Status { url, status, .. } => write!(f, " [{phase}: GET {url} -> {status}]"),
+            Self::Truncated { url } => write!(f, " [{phase}: GET {url} ended before </html>]"),
+            Self::Challenge { url, status } => write!(f, " [{phase}: GET {url} -> {status}, challenge page]"),
+            Self::Parse { url, status, missing, .. } => {
+                write!(f, " [{phase}: GET {url}")?;
+                if let Some(status) = status { write!(f, " -> {status}")?; }
+                if let Some(selector) = missing { write!(f, "; no match for `{selector}`")?; }
+                f.write_str("]")
+            }
+            Self::UnknownListing { id } => write!(f, " [{phase}: no listing `{id}`]"),
+        }
+    }
+}
@@ -0,0 +1,361 @@
+// Filename parsing for Madokami archive names, e.g.
+//   "[Group] Title v02 c013.5 (v2) (Digital).zip"
+//   "Title 2nd Season c05.cbz"
+//   "Title c001-010.zip"
+use aidoku::alloc::{String, Vec, string::ToString};
+use core::cmp::Ordering;
+
+// Split a filename into lowercase word tokens (spaces, underscores and brackets act as separators)
+pub fn filename_tokens(name: &str) -> Vec<String> {
+    name.split(|c: char| c.is_whitespace() || matches!(c, '_' | '(' | ')' | '[' | ']' | '{' | '}'))
+        .filter(|w| !w.is_empty())
+        .map(|w| w.to_ascii_lowercase())
+        .collect()
+}
+
+// Parse the number at the start of a token ("03", "13.5", "01-03" -> 1); trailing text is ignored
+pub fn leading_number(s: &str) -> Option<f32> {
+    let bytes = s.as_bytes();
+    let mut end = 0;
+    while end < bytes.len() && bytes[end].is_ascii_digit() { end += 1; }
+    if end == 0 { return None; }
+    if end + 1 < bytes.len() && bytes[end] == b'.' && bytes[end + 1].is_ascii_digit() {
+        end += 1;
+        while end < bytes.len() && bytes[end].is_ascii_digit() { end += 1; }
+    }
+    s.get(..end)?.parse::<f32>().ok()
+}
+
+// Archive/document formats the reader can open; everything else (.txt, .nfo, .sfv, .url, ...) is skipped
+const READABLE_EXTENSIONS: [&str; 10] = ["zip", "cbz", "rar", "cbr", "7z", "cb7", "tar", "cbt", "pdf", "epub"];
+
+// Lowercase extension of a filename, if it has one
+pub fn extension(name: &str) -> Option<String> {
+    let (_, ext) = name.trim().rsplit_once('.')?;
+    // "c013.5" has no extension: require a letter so decimals aren't mistaken for one
+    if ext.is_empty() || ext.len() > 5 || !ext.bytes().all(|b| b.is_ascii_alphanumeric())
+        || !ext.bytes().any(|b| b.is_ascii_alphabetic()) { return None; }
+    Some(ext.to_ascii_lowercase())
+}
+
+// Plain-text/HTML novel files, readable only inside the Novels section
+pub fn is_novel_file(name: &str) -> bool {
+    matches!(extension(name).as_deref(), Some("txt" | "html" | "htm"))
+}
+
+// Filename without its extension ("Title c01.zip" -> "Title c01")
+pub fn strip_extension(name: &str) -> &str {
+    let name = name.trim();
+    match (extension(name), name.rsplit_once('.')) {
+        (Some(_), Some((stem, _))) => stem.trim_end(),
+        _ => name,
+    }
+}
+
+// Files without a recognizable extension are kept; Madokami sometimes omits them
+pub fn is_readable_file(name: &str) -> bool {
+    extension(name).is_none_or(|ext| READABLE_EXTENSIONS.contains(&ext.as_str()))
+}
+
+// Spelled-out numbers used in titles like "Chapter Three"
+fn number_word(word: &str) -> Option<f32> {
+    const WORDS: [&str; 20] = [
+        "one", "two", "three", "four", "five", "six", "seven", "eight", "nine", "ten",
+        "eleven", "twelve", "thirteen", "fourteen", "fifteen", "sixteen", "seventeen", "eighteen", "nineteen", "twenty",
+    ];
+    WORDS.iter().position(|w| *w == word).map(|i| (i + 1) as f32)
+}
+
+// "1st", "2nd", "second", ... these describe seasons/parts, never chapters
+fn is_ordinal(tok: &str) -> bool {
+    const ORDINALS: [&str; 10] = ["first", "second", "third", "fourth", "fifth", "sixth", "seventh", "eighth", "ninth", "tenth"];
+    if ORDINALS.contains(&tok) { return true; }
+    let digits = tok.bytes().take_while(|b| b.is_ascii_digit()).count();
+    digits > 0 && matches!(tok.get(digits..), Some("st" | "nd" | "rd" | "th"))
+}
+
+// Number carried by an explicit chapter token: "c05", "c013.5", "ch.5", "ch5", "#5", "c001-010" (range start).
+// Revision suffixes ("c013v2") are ignored by leading_number. "c2c" is a release marker, not a chapter.
+fn explicit_chapter(tok: &str) -> Option<f32> {
+    if tok == "c2c" { return None; }
+    // Joined volume+chapter tokens ("v02c013.5", "vol3ch12"): the chapter part wins
+    if let Some(rest) = ["vol.", "vol", "v"].iter().find_map(|p| tok.strip_prefix(p)) {
+        let digits = rest.bytes().take_while(|b| b.is_ascii_digit() || *b == b'.').count();
+        if digits > 0 && digits < rest.len() {
+            return explicit_chapter(rest.get(digits..)?.trim_start_matches(['-', '.']));
+        }
+        return None;
+    }
+    let rest = ["chapter", "ch.", "ch", "c", "#"].iter().find_map(|p| tok.strip_prefix(p))?;
+    leading_number(rest.trim_start_matches('.'))
+}
+
+// Volume tokens: "v01", "vol.3", "Vol. 3", "Volume 03"
+pub fn parse_volume_number(name: &str) -> Option<f32> {
+    let tokens = filename_tokens(name);
+    for (i, tok) in tokens.iter().enumerate() {
+        // A bare "v2" after the chapter token is a revision marker ("c013 (v2)")
+        if tokens[..i].iter().any(|t| explicit_chapter(t).is_some()) { break; }
+        for prefix in ["volume", "vol.", "vol", "v"] {
+            let Some(rest) = tok.strip_prefix(prefix) else { continue };
+            let rest = rest.trim_start_matches('.');
+            if !rest.is_empty() {
+                if let Some(n) = leading_number(rest) { return Some(n); }
+            } else if prefix != "v" {
+                // Number in the following token ("Vol. 3")
+                if let Some(n) = tokens.get(i + 1).and_then(|t| leading_number(t)) { return Some(n); }
+            }
+            break;
+        }
+    }
+    None
+}
+
+// Chapter ranges in archive names: "c001-010", "ch.1-5", "001-010" -> (start, end)
+pub fn parse_chapter_range(name: &str) -> Option<(f32, f32)> {
+    for tok in filename_tokens(name) {
+        let body = ["ch.", "ch", "c"].iter()
+            .find_map(|p| tok.strip_prefix(p))
+            .unwrap_or(tok.as_str());
+        let Some((lo, hi)) = body.split_once('-') else { continue };
+        let hi = hi.trim_start_matches(|c: char| matches!(c, 'c' | 'h' | '.'));
+        if lo.is_empty() || !lo.bytes().all(|b| b.is_ascii_digit() || b == b'.') { continue; }
+        if let (Some(start), Some(end)) = (leading_number(lo), leading_number(hi)) {
+            if end > start { return Some((start, end)); }
+        }
+    }
+    None
+}
+
+// Chapter number, preferring explicit chapter tokens (including joined "v02c013") over volume
+// tokens and bare numbers.
+// Bare numbers skip volume/season/revision tokens, ordinals and years so
+// "Title 2nd Season c05" -> 5 and "Title v02 c013.5 (v2)" -> 13.5. Series names often carry
+// numbers of their own, so the last bare number wins ("Mob Psycho 100 - 050" -> 50), and a
+// volume archive ("Kaiju No. 8 v01") gets none.
+pub fn parse_chapter_number(name: &str) -> Option<f32> {
+    let tokens = filename_tokens(name);
+    for (i, tok) in tokens.iter().enumerate() {
+        if let Some(n) = explicit_chapter(tok) { return Some(n); }
+        // "Chapter 5", "Ch. 5", "Chapter Three"
+        if matches!(tok.as_str(), "chapter" | "ch." | "ch" | "#") {
+            if let Some(next) = tokens.get(i + 1) {
+                if let Some(n) = leading_number(next).or_else(|| number_word(next)) { return Some(n); }
+            }
+        }
+    }
+    if let Some((start, _)) = parse_chapter_range(name) { return Some(start); }
+    if parse_volume_number(name).is_some() { return None; }
+    // Only plain numbers (or a "012v2" revision) count, so group tags like "(1r0n)" are skipped
+    let tokens = filename_tokens(strip_extension(name));
+    let is_number = |tok: &str| {
+        let rest = tok.trim_start_matches(|c: char| c.is_ascii_digit() || c == '.');
+        let revision = rest.strip_prefix('v').is_some_and(|r| !r.is_empty() && r.bytes().all(|b| b.is_ascii_digit()));
+        rest.len() < tok.len() && (rest.is_empty() || revision)
+    };
+    tokens.iter().enumerate().rev().find_map(|(i, tok)| {
+        if !is_number(tok) { return None; }
+        let prev = i.checked_sub(1).and_then(|p| tokens.get(p)).map_or("", |t| t.as_str());
+        if matches!(prev, "vol" | "vol." | "volume" | "season" | "part") { return None; }
+        let n = leading_number(tok)?;
+        // Four-digit years ("(2019)") are publication dates
+        let is_year = tok.len() == 4 && (1900.0..=2100.0).contains(&n);
+        if is_year { None } else { Some(n) }
+    })
+}
+
+// Readable title from a filename: drop the extension, turn '_' and word-separating '.' into spaces
+// (decimals like "c013.5" and abbreviations like "Vol. 3" are kept) and collapse runs of whitespace
+pub fn clean_title(name: &str) -> String {
+    let stem = strip_extension(name);
+    let chars: Vec<char> = stem.chars().collect();
+    let mut out = String::with_capacity(stem.len());
+    for (i, &c) in chars.iter().enumerate() {
+        let prev = if i > 0 { chars.get(i - 1).copied() } else { None };
+        let next = chars.get(i + 1).copied();
+        let keep_dot = c == '.' && (
+            (prev.is_some_and(|p| p.is_ascii_digit()) && next.is_some_and(|n| n.is_ascii_digit()))
+            || next.is_some_and(char::is_whitespace)
+        );
+        if c == '_' || c.is_whitespace() || (c == '.' && !keep_dot) {
+            if !out.is_empty() && !out.ends_with(' ') { out.push(' '); }
+        } else {
+            out.push(c);
+        }
+    }
+    let trimmed = out.trim_end().len();
+    out.truncate(trimmed);
+    out
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum ReleaseKind {
+    Digital,
+    Scan,
+}
+
+impl ReleaseKind {
+    pub fn label(self) -> &'static str {
+        match self {
+            ReleaseKind::Digital => "Digital",
+            ReleaseKind::Scan => "Scan",
+        }
+    }
+}
+
+// Release markers: "(Digital)" / "(Digital-HD)" vs "(Scan)" / "(c2c)" (cover-to-cover scan)
+pub fn release_kind(name: &str) -> Option<ReleaseKind> {
+    filename_tokens(name).iter().find_map(|tok| {
+        if tok.starts_with("digital") || tok == "webrip" { Some(ReleaseKind::Digital) }
+        else if tok == "c2c" || tok == "scan" || tok == "scans" { Some(ReleaseKind::Scan) }
+        else { None }
+    })
+}
+
+// Group credit/recruitment pages inside archives ("zzz_credits.png", "Recruit.jpg", "credits2.png")
+pub fn is_credit_page(file: &str) -> bool {
+    let base = file.rsplit('/').next().unwrap_or(file).to_ascii_lowercase();
+    ["zzz", "credit", "recruit"].iter().any(|p| base.starts_with(p))
+        || ["credits", "recruitment", "recruiting"].iter().any(|w| base.contains(w))
+}
+
+// Side content that usually has no chapter number of its own ("Omake", "Extra", "Special", "Side Story")
+pub fn is_extra(name: &str) -> bool {
+    let tokens = filename_tokens(name);
+    tokens.iter().enumerate().any(|(i, tok)| {
+        matches!(tok.as_str(), "extra" | "extras" | "omake" | "special" | "specials" | "bonus")
+            || (tok == "side" && tokens.get(i + 1).is_some_and(|t| t.starts_with("stor")))
+    })
+}
+
+// Release group from a leading "[Group]" bracket; multiple groups may be joined with '&' or '+'
+pub fn parse_scanlators(name: &str) -> Option<Vec<String>> {
+    let rest = name.trim_start().strip_prefix('[')?;
+    let (inner, _) = rest.split_once(']')?;
+    let groups = inner.split(['&', '+'])
+        .map(|g| g.trim())
+        .filter(|g| !g.is_empty())
+        .map(|g| g.to_string())
+        .collect::<Vec<String>>();
+    if groups.is_empty() { None } else { Some(groups) }
+}
+
+// Natural, case-insensitive ordering: digit runs compare numerically so "ch 2" < "ch 10"
+pub fn natural_cmp(a: &str, b: &str) -> Ordering {
+    let (a, b) = (a.as_bytes(), b.as_bytes());
+    let (mut i, mut j) = (0, 0);
+    while i < a.len() && j < b.len() {
+        if a[i].is_ascii_digit() && b[j].is_ascii_digit() {
+            let (si, sj) = (i, j);
+            while i < a.len() && a[i].is_ascii_digit() { i += 1; }
+            while j < b.len() && b[j].is_ascii_digit() { j += 1; }
+            // Compare digit runs without parsing (no overflow): strip leading zeros, then length, then bytes
+            let ra = trim_zeros(&a[si..i]);
+            let rb = trim_zeros(&b[sj..j]);
+            let ord = ra.len().cmp(&rb.len()).then_with(|| ra.cmp(rb));
+            if ord != Ordering::Equal { return ord; }
+        } else {
+            let ord = a[i].to_ascii_lowercase().cmp(&b[j].to_ascii_lowercase());
+            if ord != Ordering::Equal { return ord; }
+            i += 1;
+            j += 1;
+        }
+    }
+    (a.len() - i).cmp(&(b.len() - j))
+}
+
+fn trim_zeros(digits: &[u8]) -> &[u8] {
+    let start = digits.iter().position(|d| *d != b'0').unwrap_or(digits.len());
+    &digits[start..]
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use aidoku_test::aidoku_test;
+
+    #[aidoku_test]
+    fn chapter_number_skips_ordinals_and_seasons() {
+        assert_eq!(parse_chapter_number("Title 2nd Season c05.cbz"), Some(5.0));
+        assert_eq!(parse_chapter_number("Title 1st Part c12.zip"), Some(12.0));
+    }
+
+    #[aidoku_test]
+    fn chapter_number_ignores_revision_suffixes() {
+        assert_eq!(parse_chapter_number("Title v02 c013.5 (v2).zip"), Some(13.5));
+        assert_eq!(parse_chapter_number("[Group] Title v02 c013.5 (v2) (Digital).zip"), Some(13.5));
+        assert_eq!(parse_chapter_number("Title c013v2.zip"), Some(13.0));
+    }
+
+    #[aidoku_test]
+    fn chapter_number_prefixes() {
+        assert_eq!(parse_chapter_number("Title ch.5.zip"), Some(5.0));
+        assert_eq!(parse_chapter_number("Title Ch. 12.zip"), Some(12.0));
+        assert_eq!(parse_chapter_number("Title #42.cbz"), Some(42.0));
+        assert_eq!(parse_chapter_number("Title Chapter 3.zip"), Some(3.0));
+        assert_eq!(parse_chapter_number("Title c01 (c2c).zip"), Some(1.0));
+    }
+
+    #[aidoku_test]
+    fn chapter_number_bare_numbers() {
+        assert_eq!(parse_chapter_number("Title (2019) 12.zip"), Some(12.0));
+        assert_eq!(parse_chapter_number("Title Vol. 3.zip"), None);
+        assert_eq!(parse_chapter_number("Title.zip"), None);
+    }
+
+    #[aidoku_test]
+    fn chapter_number_skips_numbers_in_series_names() {
+        assert_eq!(parse_chapter_number("Mob Psycho 100 - 050.zip"), Some(50.0));
+        assert_eq!(parse_chapter_number("Kaiju No. 8 - 105 (2023) (Digital) (1r0n).cbz"), Some(105.0));
+        assert_eq!(parse_chapter_number("20th Century Boys 005.zip"), Some(5.0));
+        assert_eq!(parse_chapter_number("Ajin - Demi-Human 086.zip"), Some(86.0));
+        assert_eq!(parse_chapter_number("Mob Psycho 100 c050 [Hot Chocolate Scans].zip"), Some(50.0));
+        assert_eq!(parse_chapter_number("Kaiju No. 8 - 012v2 (1r0n).zip"), Some(12.0));
+    }
+
+    #[aidoku_test]
+    fn chapter_number_of_ranges_is_the_start() {
+        assert_eq!(parse_chapter_number("Title c001-010.zip"), Some(1.0));
+        assert_eq!(parse_chapter_number("Title 001-010.zip"), Some(1.0));
+    }
+
+    #[aidoku_test]
+    fn joined_volume_and_chapter_tokens() {
+        assert_eq!(parse_chapter_number("Title v02c013.5.zip"), Some(13.5));
+        assert_eq!(parse_volume_number("Title v02c013.5.zip"), Some(2.0));
+        assert_eq!(parse_chapter_number("Title vol3ch12.zip"), Some(12.0));
+        assert_eq!(parse_volume_number("Title vol3ch12.zip"), Some(3.0));
+        assert_eq!(parse_chapter_number("Title v02 c013.5.zip"), Some(13.5));
+        // A volume token alone never supplies the chapter number
+        assert_eq!(parse_chapter_number("Title v02.zip"), None);
+    }
+
+    #[aidoku_test]
+    fn volume_archives_have_no_chapter_number() {
+        // Numbers in the series name aren't chapters
+        assert_eq!(parse_chapter_number("Mob Psycho 100 v01.zip"), None);
+        assert_eq!(parse_chapter_number("Kaiju No. 8 v01.zip"), None);
+        assert_eq!(parse_chapter_number("20th Century Boys v22 (2007) (Digital).zip"), None);
+        assert_eq!(parse_volume_number("Mob Psycho 100 v01.zip"), Some(1.0));
+        // Volume archives that spell out their chapters still get the first one
+        assert_eq!(parse_chapter_number("Kaiju No. 8 v01 c001-007.zip"), Some(1.0));
+    }
+
+    #[aidoku_test]
+    fn volume_number() {
+        assert_eq!(parse_volume_number("Title v02 c013.5 (v2).zip"), Some(2.0));
+        assert_eq!(parse_volume_number("Title Vol. 3 Ch. 12.zip"), Some(3.0));
+        assert_eq!(parse_volume_number("Title Volume 03.zip"), Some(3.0));
+        // "(v2)" after the chapter token is a revision, not a volume
+        assert_eq!(parse_volume_number("Title c013 (v2).zip"), None);
+    }
+
+    #[aidoku_test]
+    fn chapter_range() {
+        assert_eq!(parse_chapter_range("Title c001-010.zip"), Some((1.0, 10.0)));
+        assert_eq!(parse_chapter_range("Title ch.1-5.zip"), Some((1.0, 5.0)));
+        assert_eq!(parse_chapter_range("Title 001-010 (Digital).zip"), Some((1.0, 10.0)));
+        assert_eq!(parse_chapter_range("Title c010-001.zip"), None);
+        assert_eq!(parse_chapter_range("Title c05.zip"), None);
+    }
+}
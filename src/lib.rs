@@ -1,51 +1,94 @@
 #![no_std]
 
+extern crate alloc;
+
 use aidoku::{
-    Chapter, ContentRating, DeepLinkHandler, DeepLinkResult, FilterValue, Home, HomeLayout, Listing,
-    ListingProvider, Manga, MangaPageResult, MangaStatus, Page, PageContent, Result, Source, Viewer,
+    Chapter, ContentRating, DeepLinkHandler, DeepLinkResult, FilterValue, ImageRequestProvider, Listing, ListingProvider,
+    Manga, MangaPageResult, MangaStatus, NotificationHandler, Page, PageContent, PageContext, Result, Source, Viewer,
     alloc::{String, Vec, string::ToString},
-    imports::{defaults::defaults_get, net::*},
+    imports::{
+        defaults::{defaults_get, defaults_set, DefaultValue},
+        std::current_date,
+        html::{Document, Element, ElementList, Html},
+        net::*,
+    },
     prelude::*,
 };
-use base64::{engine::general_purpose, Engine as _};
+#[cfg(feature = "home")]
+use aidoku::{Home, HomeComponent, HomeComponentValue, HomeLayout, Link, LinkValue};
+use alloc::collections::{BTreeMap, BTreeSet};
 use core::fmt::Write as _; // for simple string building
 
-// Lightweight percent-decoder (handles %XX and + -> space)
+mod cache;
+mod date;
+mod error;
+mod filename;
+mod selectors;
+mod url;
+mod util;
+use error::{MadokamiError, Target};
+use selectors::Chain;
+use url::{encode_path, MadokamiUrl};
+use filename::{clean_title, extension, is_credit_page, is_extra, is_novel_file, release_kind, ReleaseKind, is_readable_file, natural_cmp, strip_extension, parse_chapter_number, parse_chapter_range, parse_scanlators, parse_volume_number};
+
+// Lightweight percent-decoder (handles %XX and + -> space). Works on bytes so encoded UTF-8
+// sequences decode to their characters; segments without escapes are copied once.
 fn percent_decode(input: &str) -> String {
+    if !input.contains(['%', '+']) { return input.into(); }
     let bytes = input.as_bytes();
-    let mut out = String::new();
+    let mut out = Vec::with_capacity(bytes.len());
     let mut i = 0;
     while i < bytes.len() {
-        match bytes[i] as char {
-            '%' if i + 2 < bytes.len() => {
-                let h = (bytes[i+1] as char, bytes[i+2] as char);
-                if let (Some(hi), Some(lo)) = (hex(h.0), hex(h.1)) { out.push(((hi << 4) | lo) as char); i += 3; } else { out.push('%'); i += 1; }
-            }
-            '+' => { out.push(' '); i += 1; }
-            c => { out.push(c); i += 1; }
+        match bytes[i] {
+            // A trailing "%X" or "%" has no escape to decode and is kept as-is
+            b'%' => match bytes.get(i + 1..i + 3).map(|h| (hex(h[0]), hex(h[1]))) {
+                Some((Some(hi), Some(lo))) => { out.push((hi << 4) | lo); i += 3; }
+                _ => { out.push(b'%'); i += 1; }
+            },
+            b'+' => { out.push(b' '); i += 1; }
+            b => { out.push(b); i += 1; }
         }
     }
-    out
+    String::from_utf8(out).unwrap_or_else(|e| String::from_utf8_lossy(e.as_bytes()).into_owned())
 }
 
-fn hex(c: char) -> Option<u8> {
-    match c {
-        '0'..='9' => Some(c as u8 - b'0'),
-        'a'..='f' => Some(10 + c as u8 - b'a'),
-        'A'..='F' => Some(10 + c as u8 - b'A'),
+fn hex(b: u8) -> Option<u8> {
+    match b {
+        b'0'..=b'9' => Some(b - b'0'),
+        b'a'..=b'f' => Some(10 + b - b'a'),
+        b'A'..=b'F' => Some(10 + b - b'A'),
         _ => None,
     }
 }
 
-// Encode strictly for query component (space -> %20, etc.)
-fn encode_component(s: &str) -> String {
-    let mut out = String::new();
-    for b in s.bytes() {
-        let c = b as char;
-        if c.is_ascii_alphanumeric() || matches!(c, '-'|'_'|'.'|'~') { out.push(c); } else {
-            let _ = write!(out, "%{:02X}", b);
+// Decode the HTML entities that show up in escaped attribute values (&quot; &amp; &#39; &#x27; ...)
+fn unescape_html(input: &str) -> String {
+    let mut out = String::with_capacity(input.len());
+    let mut rest = input;
+    while let Some(amp) = rest.find('&') {
+        out.push_str(&rest[..amp]);
+        let tail = &rest[amp..];
+        let decoded = tail.find(';').filter(|end| *end <= 10).and_then(|end| {
+            let entity = tail.get(1..end)?;
+            let c = match entity {
+                "quot" => Some('"'),
+                "amp" => Some('&'),
+                "apos" => Some('\''),
+                "lt" => Some('<'),
+                "gt" => Some('>'),
+                _ => entity.strip_prefix("#x").or_else(|| entity.strip_prefix("#X"))
+                    .and_then(|h| u32::from_str_radix(h, 16).ok())
+                    .or_else(|| entity.strip_prefix('#').and_then(|d| d.parse::<u32>().ok()))
+                    .and_then(char::from_u32),
+            };
+            c.map(|c| (c, end + 1))
+        });
+        match decoded {
+            Some((c, len)) => { out.push(c); rest = tail.get(len..).unwrap_or_default(); }
+            None => { out.push('&'); rest = tail.get(1..).unwrap_or_default(); }
         }
     }
+    out.push_str(rest);
     out
 }
 
@@ -54,16 +97,220 @@ fn derive_from_path(path: &str) -> (String, Option<String>) {
     let segs: Vec<&str> = path.split('/')
         .filter(|s| !s.is_empty())
         .collect();
-    if segs.is_empty() { return (String::new(), None); }
+    let Some(last) = segs.last() else { return (String::new(), None) };
     // description = last segment decoded
-    let description = percent_decode(segs.last().unwrap());
+    let description = percent_decode(last);
     // Walk backwards until a segment not starting with '!'
     let mut title = String::new();
     for seg in segs.iter().rev() {
         let dec = percent_decode(seg);
         if !dec.starts_with('!') { title = dec; break; }
     }
-    (title, Some(description))
+    (localized_title(&title), Some(description))
+}
+
+// Folders sometimes carry both names ("進撃の巨人 (Attack on Titan)", "Shingeki / 進撃の巨人");
+// keep the one matching the titleLocale setting. Names in the same script are left alone.
+fn localized_title(title: &str) -> String {
+    let prefer_native = match defaults_get::<String>("titleLocale").as_deref() {
+        Some("english") => false,
+        Some("native") => true,
+        _ => return title.to_string(),
+    };
+    let pair = title.split_once(" / ")
+        .or_else(|| title.strip_suffix(')').and_then(|inner| inner.rsplit_once(" (")));
+    let Some((first, second)) = pair.map(|(a, b)| (a.trim(), b.trim())) else { return title.to_string() };
+    if first.is_empty() || second.is_empty() || first.is_ascii() == second.is_ascii() { return title.to_string(); }
+    let native = if first.is_ascii() { second } else { first };
+    let english = if first.is_ascii() { first } else { second };
+    if prefer_native { native.to_string() } else { english.to_string() }
+}
+
+// Canonicalize key similar to Tachiyomi logic for /Manga/ and /Raws/ variants
+fn canonical_series_key(key: &str) -> String {
+    if key.starts_with("/Manga/") {
+        // Pattern: /Manga/<Initial>/<Title>/... remove extra segments beyond 5
+        // Split and if length > 6 ("", "Manga", initial, title, maybe extra) trim to first 5 segments
+        let parts: Vec<&str> = key.split('/').collect();
+        if parts.len() > 6 && parts.get(2).map(|s| s.len() == 1).unwrap_or(false) {
+            // Keep: "", "Manga", initial, title, next two segments (index 0..=5)
+            if let Some(kept) = parts.get(..=5) { return normalize_chapter_href(&kept.join("/")); }
+        }
+    } else if key.starts_with("/Raws/") {
+        // Raws are filed as /Raws/<Language>/<Series>: trim trailing '!' segments below the series
+        let mut parts: Vec<&str> = key.split('/').collect();
+        while parts.len() > 4 && parts.last().is_some_and(|last| last.starts_with('!')) {
+            parts.pop();
+        }
+        return normalize_chapter_href(&parts.join("/"));
+    }
+    key.to_string()
+}
+
+// Series that a reader link belongs to: the `path=` query parameter or the path after /reader,
+// minus the file name, canonicalized like any series key
+fn series_key_for_reader(reader_key: &str) -> String {
+    // Path segments are kept as the site encoded them so keys match the hrefs seen in listings
+    let file_path = reader_key.split_once('?')
+        .and_then(|(_, query)| query.split('&').find_map(|kv| kv.strip_prefix("path=")))
+        .map(|p| encode_path(&percent_decode(p)))
+        .unwrap_or_else(|| {
+            let path = reader_key.strip_prefix("/reader").unwrap_or(reader_key);
+            path.split(['?', '#']).next().unwrap_or(path).to_string()
+        });
+    let parent = file_path.trim_end_matches('/').rsplit_once('/').map_or("", |(dir, _)| dir);
+    canonical_series_key(parent)
+}
+
+// Site root: the "baseUrl" override when it is a valid http(s) URL, otherwise BASE_URL.
+// A path prefix is allowed for self-hosted proxies; a trailing '/' is dropped.
+fn base_url() -> String {
+    defaults_get::<String>("baseUrl")
+        .map(|url| url.trim().trim_end_matches('/').to_string())
+        .filter(|url| is_valid_base_url(url))
+        .unwrap_or_else(|| BASE_URL.into())
+}
+
+fn is_valid_base_url(url: &str) -> bool {
+    let Some(rest) = url.strip_prefix("https://").or_else(|| url.strip_prefix("http://")) else { return false };
+    let host = rest.split('/').next().unwrap_or_default();
+    let host_ok = host.contains('.') || host.starts_with("localhost");
+    host_ok && !url.contains(|c: char| c.is_whitespace() || c == '?' || c == '#')
+}
+
+fn url_host(url: &str) -> &str {
+    let rest = url.split_once("://").map_or(url, |(_, rest)| rest);
+    rest.split('/').next().unwrap_or(rest)
+}
+
+// Hosts whose links are handled: BASE_URL's, the base URL override's and the comma-separated
+// "mirrorHosts" setting
+fn link_hosts() -> Vec<String> {
+    let mut hosts = alloc::vec![url_host(BASE_URL).to_string()];
+    let base = base_url();
+    let base_host = url_host(&base);
+    let base_host = base_host.strip_prefix("www.").unwrap_or(base_host);
+    if !hosts.iter().any(|h| h.eq_ignore_ascii_case(base_host)) { hosts.push(base_host.to_string()); }
+    if let Some(mirrors) = defaults_get::<String>("mirrorHosts") {
+        for mirror in mirrors.split(',') {
+            let mirror = mirror.trim();
+            let mirror = mirror.strip_prefix("https://").or_else(|| mirror.strip_prefix("http://")).unwrap_or(mirror);
+            let mirror = mirror.trim_end_matches('/');
+            let mirror = mirror.strip_prefix("www.").unwrap_or(mirror);
+            if !mirror.is_empty() { hosts.push(mirror.to_string()); }
+        }
+    }
+    hosts
+}
+
+// Site-relative key for a link to one of link_hosts(). Accepts http/https, a "www." prefix, host case
+// differences and trailing slashes; the path is percent-decoded and re-encoded so links copied from
+// browsers (decoded) and chat apps (encoded) produce the same key. The fragment is dropped and any
+// query is kept verbatim for the caller to interpret.
+fn deep_link_key(url: &str) -> Option<String> {
+    // Fragments ("#index") are client-side only
+    let rest = url.trim().split('#').next().unwrap_or_default();
+    let rest = rest.strip_prefix("https://").or_else(|| rest.strip_prefix("http://")).unwrap_or(rest);
+    let rest = rest.strip_prefix("www.").unwrap_or(rest);
+    let (link_host, path) = rest.split_at(rest.find(['/', '?']).unwrap_or(rest.len()));
+    if !link_hosts().iter().any(|host| link_host.eq_ignore_ascii_case(host)) { return None; }
+    let (path, query) = match path.split_once('?') {
+        Some((p, q)) => (p, Some(q)),
+        None => (path, None),
+    };
+    // '+' is literal in paths; only the query treats it as a space
+    let decoded = percent_decode(&path.replace('+', "%2B"));
+    let mut key = encode_path(decoded.trim_end_matches('/'));
+    if key.is_empty() { key.push('/'); }
+    if let Some(query) = query {
+        key.push('?');
+        key.push_str(query);
+    }
+    Some(normalize_chapter_href(&key))
+}
+
+// Series entry named after its folder path
+fn entry_for_key(key: String, base: &str) -> Option<Manga> {
+    let (title, description) = derive_from_path(&key);
+    if title.is_empty() { return None; }
+    let url = Some(MadokamiUrl::with_base(base).path(&key).build());
+    Some(Manga { key, title, description: description.filter(|d| !d.is_empty()), url, ..Default::default() })
+}
+
+// Table rows whose first link is a series folder (search, tag and recent pages) as entries
+fn row_entries(html: &Document, rows: &Chain) -> Vec<Manga> {
+    let base = base_url();
+    rows.all(html).into_iter().filter_map(|row| {
+        let link = row.select_first("td:nth-child(1) a:nth-child(1)")?;
+        entry_for_key(link.attr("href")?, &base)
+    }).collect()
+}
+
+// Result rows of the search page (also used by tag pages, which share its layout)
+fn search_entries(html: &Document) -> Vec<Manga> {
+    row_entries(html, &selectors::SEARCH_ROWS)
+}
+
+// Entries the user has chosen not to browse
+fn is_hidden_entry(key: &str, hide_raws: bool, show_adult: bool) -> bool {
+    let under = |section: &str| key.get(..section.len()).is_some_and(|p| p.eq_ignore_ascii_case(section))
+        && matches!(key.as_bytes().get(section.len()), None | Some(b'/'));
+    (hide_raws && under("/Raws")) || (!show_adult && ADULT_SECTIONS.iter().any(|s| under(s)))
+}
+
+fn visible_entries(mut entries: Vec<Manga>) -> Vec<Manga> {
+    let hide_raws = defaults_get::<bool>("hideRaws").unwrap_or(false);
+    let show_adult = defaults_get::<bool>("adultSections").unwrap_or(false);
+    entries.retain(|m| !is_hidden_entry(&m.key, hide_raws, show_adult));
+    entries
+}
+
+// Folders above series level: top-level sections ("/Raws"), the /Manga/<A>/<AB>/<ABCD> indexes and
+// the /Raws/<Language> folders (see raw_language)
+fn is_intermediate_directory(key: &str) -> bool {
+    let segs = key.split('/').filter(|s| !s.is_empty()).count();
+    segs == 1 || (key.starts_with("/Manga") && segs < 5) || (key.starts_with("/Raws/") && segs < 3)
+}
+
+// Series folders a library entry may be moved to: below the section indexes of /Manga, /Raws or
+// /Novels, never a file or a section/index page a redirect happened to land on
+fn is_series_key(key: &str) -> bool {
+    ["/Manga/", "/Raws/", "/Novels/"].iter().any(|section| key.starts_with(section))
+        && !is_intermediate_directory(key) && extension(key).is_none()
+}
+
+// Subfolders of a directory index as browsable entries
+// (same table lookup as chapter lists; any row with a file extension is a file, not a folder)
+fn directory_entries(html: &Document, dir: &str) -> Vec<Manga> {
+    let prefix = format!("{}/", dir.trim_end_matches('/'));
+    let Some((rows, cols)) = index_rows(html) else { return Vec::new() };
+    let base = base_url();
+    rows.filter_map(|row| {
+        let link = row.select_first(&format!("td:nth-child({}) a", cols.name))?;
+        let key = link.attr("href")?.trim_end_matches('/').to_string();
+        if !key.starts_with(&prefix) || extension(&key).is_some() { return None; }
+        entry_for_key(key, &base)
+    }).collect()
+}
+
+// Language code for series filed under /Raws/<Language>/...
+fn raw_language(key: &str) -> Option<&'static str> {
+    let lang = percent_decode(key.strip_prefix("/Raws/")?.split('/').next()?).to_ascii_lowercase();
+    Some(match lang.as_str() {
+        "japanese" | "jp" => "ja",
+        "korean" | "kr" => "ko",
+        "chinese" | "cn" => "zh",
+        "french" => "fr",
+        "spanish" => "es",
+        "german" => "de",
+        "italian" => "it",
+        "portuguese" => "pt",
+        "russian" => "ru",
+        "vietnamese" => "vi",
+        "thai" => "th",
+        "indonesian" => "id",
+        _ => return None,
+    })
 }
 
 // Normalize reader path: ensure stored chapter key starts with the original anchor href (already contains /reader or needs prefixing) & always relative (leading '/').
@@ -71,65 +318,1096 @@ fn normalize_chapter_href(raw: &str) -> String {
     if raw.starts_with('/') { raw.to_string() } else { format!("/{}", raw) }
 }
 
-// Parse relative date strings like "5 min ago" or absolute format yyyy-MM-dd HH:mm.
-fn parse_chapter_date(raw: &str) -> i64 {
-    if raw.is_empty() { return 0; }
-    if raw.ends_with("ago") {
-        let parts: Vec<&str> = raw.split(' ').collect();
-        if parts.len() >= 2 { if let Ok(amount) = parts[0].parse::<i64>() {
-            return simulate_relative(parts[1], amount);
-        }}
-        return 0;
-    }
-    // Absolute date: yyyy-MM-dd HH:mm (naive parsing)
-    if raw.len() >= 16 { // 16 = 10 date + 1 space + 5 time
-        let year = raw[0..4].parse::<i32>().unwrap_or(1970);
-        let month = raw[5..7].parse::<i32>().unwrap_or(1);
-        let day = raw[8..10].parse::<i32>().unwrap_or(1);
-        let hour = raw[11..13].parse::<i32>().unwrap_or(0);
-        let minute = raw[14..16].parse::<i32>().unwrap_or(0);
-        // crude unix time approximation: convert to minutes ignoring leap seconds; not perfect but stable.
-        // days since epoch (naive) * 86400 + hour*3600 + minute*60
-        let days = days_since_epoch(year, month, day);
-        return days as i64 * 86400 + hour as i64 * 3600 + minute as i64 * 60;
-    }
-    0
-}
-
-fn simulate_relative(unit: &str, amount: i64) -> i64 {
-    // We cannot access current time reliably in no_std; return relative offset as negative seconds from pseudo-now (0).
-    // Aidoku may adjust; using 0 - delta gives ordering semantics.
-    let secs = if unit.starts_with("min") { amount * 60 }
-        else if unit.starts_with("hour") { amount * 3600 }
-        else if unit.starts_with("sec") { amount }
-        else { 0 };
-    -secs
-}
-
-fn days_since_epoch(y: i32, m: i32, d: i32) -> i32 { // Gregorian calendar simple calc
-    // Source: civil date to days from epoch algorithm (public domain adaptation).
-    let y = y - (m <= 2) as i32;
-    let era = (if y >= 0 { y } else { y - 399 }) / 400;
-    let yoe = y - era * 400;
-    let doy = (153 * (m + (if m > 2 { -3 } else { 9 })) + 2) / 5 + d - 1;
-    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
-    era * 146097 + doe - 719468 // days since 1970-01-01
+// Offset (seconds east of UTC) of the timezone the index table's absolute dates are rendered in
+fn server_utc_offset() -> i64 {
+    defaults_get::<String>("serverTimezone")
+        .and_then(|v| date::parse_utc_offset(&v))
+        .unwrap_or(0)
+}
+
+// Estimate when the next upload should land: latest upload + median gap between the most recent uploads.
+// Once that moment has passed the series is overdue, so the next check backs off from now by the median
+// gap or the time since the last upload (up to MAX_UPDATE_BACKOFF): dormant series are checked rarely.
+fn estimate_next_update(chapters: &[Chapter]) -> Option<i64> {
+    let mut dates: Vec<i64> = chapters.iter()
+        .filter_map(|c| c.date_uploaded)
+        .filter(|d| *d > 0)
+        .collect();
+    if dates.len() < 2 { return None; }
+    dates.sort_unstable();
+    dates.dedup();
+    // Only look at the latest uploads so old release cadence doesn't skew the estimate
+    let recent = &dates[dates.len().saturating_sub(MAX_UPDATE_SAMPLES + 1)..];
+    let mut gaps: Vec<i64> = recent.windows(2).map(|w| w[1] - w[0]).filter(|g| *g > 0).collect();
+    if gaps.is_empty() { return None; }
+    gaps.sort_unstable();
+    let median = gaps[gaps.len() / 2];
+    let now = current_date();
+    recent.last().map(|latest| {
+        let expected = latest + median;
+        if expected > now { expected } else { now + median.max((now - latest).min(MAX_UPDATE_BACKOFF)) }
+    })
 }
 
 const BASE_URL: &str = "https://manga.madokami.al";
+const DEFAULT_USER_AGENT: &str = "Mozilla/5.0 (iPhone; CPU iPhone OS 17_5 like Mac OS X) AppleWebKit/605.1.15 (KHTML, like Gecko) Version/17.5 Mobile/15E148 Safari/604.1";
+const MAX_UPDATE_SAMPLES: usize = 10;
+const MAX_UPDATE_BACKOFF: i64 = 30 * 24 * 60 * 60;
+// Listing ids carrying a search query, created for search deep links
+const SEARCH_LISTING_PREFIX: &str = "search:";
+// Listing ids browsing an intermediate directory, created for directory deep links
+const DIRECTORY_LISTING_PREFIX: &str = "dir:";
+// Listing ids for a genre's series, created for genre deep links
+const GENRE_LISTING_PREFIX: &str = "genre:";
+const MAX_SUBDIR_DEPTH: usize = 2;
+const MAX_INDEX_PAGES: usize = 50;
+const PAGE_THUMBNAIL_WIDTH: u32 = 200;
+const DEFAULT_RETRIES: usize = 1;
+const MAX_REDIRECTS: usize = 5;
+#[cfg(feature = "novels")]
+const NOVEL_PAGE_CHARS: usize = 4000;
+const READER_CACHE_TTL: i64 = 24 * 60 * 60;
+// Search, listing and series pages are reused for this long while browsing
+const RESPONSE_CACHE_TTL: i64 = 10 * 60;
+const RECENT_FEED_TTL: i64 = 2 * 60;
+#[cfg(feature = "home")]
+const HOME_FEATURED_COUNT: usize = 5;
+// Folders offered as browse links on the home page. Only folders whose subfolders open as series:
+// /Manga, /Raws and /Manga/_Doujinshi sit index levels above theirs and would dead-end after one.
+#[cfg(feature = "home")]
+const HOME_SECTIONS: &[(&str, &str)] = &[("/Novels", "Novels")];
+const MAX_ARCHIVE_PARTS: usize = 10;
+// Top-level folders holding adult content, hidden unless the user opts in
+const ADULT_SECTIONS: &[&str] = &["/Manga/_Doujinshi", "/Manga/_Hentai", "/Doujinshi", "/Hentai"];
 
 // =================================================================================
 // AUTHENTICATED REQUEST
 // =================================================================================
 fn auth_get(url: &str) -> Result<Request> {
+    let mut req = Request::get(url)?;
+    authorize(&mut req);
+    Ok(req)
+}
+
+// Configured User-Agent, falling back to a mobile Safari one
+fn user_agent() -> String {
+    defaults_get::<String>("userAgent")
+        .map(|ua| ua.trim().to_string())
+        .filter(|ua| !ua.is_empty())
+        .unwrap_or_else(|| DEFAULT_USER_AGENT.into())
+}
+
+// Seconds to wait for a response before giving up; None keeps the platform default
+fn request_timeout() -> Option<f64> {
+    defaults_get::<String>("requestTimeout")
+        .and_then(|t| t.parse::<f64>().ok())
+        .filter(|t| *t > 0.0)
+}
+
+// Client settings shared by every request, then Basic auth when any credentials are set
+fn authorize(req: &mut Request) {
+    if let Some(secs) = request_timeout() { req.set_timeout(secs); }
+    req.set_header("User-Agent", &user_agent());
     let username = defaults_get::<String>("username").unwrap_or_default();
     let password = defaults_get::<String>("password").unwrap_or_default();
-    let mut req = Request::get(url)?;
     if !username.is_empty() || !password.is_empty() {
-        let encoded = general_purpose::STANDARD.encode(format!("{}:{}", username, password));
+        let encoded = util::base64_encode(format!("{}:{}", username, password).as_bytes());
         req.set_header("Authorization", &format!("Basic {}", encoded));
     }
-    Ok(req)
+}
+
+fn debug_enabled() -> bool {
+    defaults_get::<bool>("debugLogging").unwrap_or(false)
+}
+
+// Authenticated, compressed GET retried on network errors and 5xx responses. Redirects to the
+// site are followed and remembered for moved_key; other statuses are returned as-is.
+fn send_with_retry(url: &str, retries: usize, headers: &[(&str, &str)]) -> Result<Response> {
+    let mut target = String::from(url);
+    let mut attempt = 0;
+    let mut redirects = 0;
+    loop {
+        let mut req = auth_get(&target)?;
+        // Index tables of large series compress well; the app's HTTP stack inflates the body
+        // transparently, so responses reach get_html/get_string already decoded
+        req.set_header("Accept-Encoding", "gzip, deflate");
+        for (name, value) in headers { req.set_header(name, value); }
+        match req.send() {
+            Ok(resp) if resp.status_code() >= 500 && attempt < retries => {}
+            Ok(resp) if matches!(resp.status_code(), 301 | 302 | 303 | 307 | 308) && redirects < MAX_REDIRECTS => {
+                let Some(next) = resp.get_header("Location").and_then(|loc| redirect_target(&loc)) else { return Ok(resp) };
+                cache::set(&redirect_cache_key(url), &next);
+                target = next;
+                redirects += 1;
+                continue;
+            }
+            Ok(resp) => return Ok(resp),
+            Err(_) if attempt < retries => {}
+            Err(_) => return MadokamiError::Network { url: target, attempts: attempt + 1 }.fail(),
+        }
+        attempt += 1;
+    }
+}
+
+// Absolute URL for a Location header pointing back at the site; redirects elsewhere aren't followed
+fn redirect_target(location: &str) -> Option<String> {
+    let key = if location.starts_with('/') { normalize_chapter_href(location) } else { deep_link_key(location)? };
+    Some(MadokamiUrl::new().path(&key).build())
+}
+
+fn redirect_cache_key(url: &str) -> String {
+    format!("redirect.{}", url)
+}
+
+// Site key a recently fetched URL redirected to (a renamed or merged folder), if it moved
+fn moved_key(url: &str) -> Option<String> {
+    cache::get(&redirect_cache_key(url), RESPONSE_CACHE_TTL)
+        .and_then(|target| deep_link_key(&target))
+        .filter(|key| MadokamiUrl::new().path(key).build() != url)
+}
+
+// Number of automatic retries for failed requests, from the retryCount setting
+fn retry_count() -> usize {
+    defaults_get::<String>("retryCount")
+        .and_then(|n| n.parse::<usize>().ok())
+        .unwrap_or(DEFAULT_RETRIES)
+}
+
+// Authenticated GET (with retries) parsed as HTML, with request errors converted to the source's Result
+fn fetch_html(url: &str) -> Result<Document> {
+    let response = send_with_retry(url, retry_count(), &[])?;
+    let status = response.status_code();
+    let body = sanitize_html(response.get_string()?);
+    if is_challenge(&body) { return MadokamiError::Challenge { url: url.into(), status }.fail(); }
+    if status >= 400 { return MadokamiError::Status { url: url.into(), status, target: Target::Page }.fail(); }
+    if is_truncated(&body) { return MadokamiError::Truncated { url: url.into() }.fail(); }
+    parse_page(url, status, &body)
+}
+
+// Drop what trips the HTML parser: a UTF-8 BOM and stray NUL characters
+fn sanitize_html(body: String) -> String {
+    let body = match body.strip_prefix('\u{feff}') { Some(rest) => rest.into(), None => body };
+    if body.contains('\0') { body.replace('\0', "") } else { body }
+}
+
+// A document that opens <html> but never closes it was cut off in transit; parsing it would
+// quietly drop every row after the cut
+fn is_truncated(body: &str) -> bool {
+    let bytes = body.as_bytes();
+    let head = &bytes[..bytes.len().min(1024)];
+    let tail = &bytes[bytes.len().saturating_sub(1024)..];
+    contains_ignore_case(head, b"<html") && !contains_ignore_case(tail, b"</html")
+}
+
+// Browser checks and maintenance pages placed in front of the site. Their markers sit in the
+// document head, so listings whose filenames happen to contain the words aren't affected.
+const CHALLENGE_MARKERS: &[&[u8]] = &[
+    b"<title>just a moment",
+    b"<title>attention required",
+    b"<title>ddos-guard",
+    b"<title>site maintenance",
+    b"<title>down for maintenance",
+    b"challenge-platform",
+    b"cf-browser-verification",
+    b"checking your browser before accessing",
+];
+
+fn is_challenge(body: &str) -> bool {
+    let bytes = body.as_bytes();
+    let head = &bytes[..bytes.len().min(4096)];
+    CHALLENGE_MARKERS.iter().any(|marker| contains_ignore_case(head, marker))
+}
+
+fn contains_ignore_case(haystack: &[u8], needle: &[u8]) -> bool {
+    haystack.windows(needle.len()).any(|w| w.eq_ignore_ascii_case(needle))
+}
+
+fn parse_page(url: &str, status: i32, body: &str) -> Result<Document> {
+    match Html::parse_with_url(body, url) {
+        Ok(doc) => Ok(doc),
+        Err(_) => MadokamiError::Parse {
+            url: url.into(),
+            status: Some(status),
+            missing: None,
+            reason: "Madokami returned a page that couldn't be read",
+        }.fail(),
+    }
+}
+
+fn response_cache_key(url: &str) -> String {
+    format!("html.{}", url)
+}
+
+// cached_html for several pages at once: cache misses are sent concurrently. Pages that fail
+// to load come back as None.
+#[cfg(feature = "home")]
+fn cached_html_all(urls: &[String]) -> Vec<Option<Document>> {
+    let mut docs = urls.iter()
+        .map(|url| cache::get(&response_cache_key(url), RESPONSE_CACHE_TTL).and_then(|body| Html::parse_with_url(&body, url).ok()))
+        .collect::<Vec<Option<Document>>>();
+    let missing = (0..urls.len()).filter(|i| docs[*i].is_none()).collect::<Vec<usize>>();
+    let Ok(requests) = missing.iter().map(|i| auth_get(&urls[*i])).collect::<Result<Vec<Request>>>() else { return docs };
+    for (i, response) in missing.into_iter().zip(Request::send_all(requests)) {
+        let Ok(response) = response else { continue };
+        if response.status_code() != 200 { continue; }
+        let Ok(body) = response.get_string().map(sanitize_html) else { continue };
+        if is_truncated(&body) || is_challenge(&body) { continue; }
+        cache::set(&response_cache_key(&urls[i]), &body);
+        docs[i] = Html::parse_with_url(&body, &urls[i]).ok();
+    }
+    docs
+}
+
+// Like fetch_html, but served from the response cache while fresh. `refresh` skips the cached
+// copy for callers that must see the latest listing; those still revalidate with ETag /
+// Last-Modified, so unchanged indexes cost a 304 instead of a full download.
+fn cached_html(url: &str, refresh: bool) -> Result<Document> {
+    match load_html(url, refresh, false)? {
+        Fetched::Page(doc) => Ok(doc),
+        Fetched::NotModified => fetch_html(url),
+        Fetched::Refused(error) => error.fail(),
+    }
+}
+
+// Outcome of load_html
+enum Fetched {
+    Page(Document),
+    // 304 for a caller that already holds what it builds from the page
+    NotModified,
+    // A 4xx status; whether that fails the caller's whole operation is up to the caller
+    Refused(MadokamiError),
+}
+
+// cached_html for callers that handle refusals themselves, or that already hold what they would
+// build from the page (`held`): a 304 then comes back as NotModified, whether or not the body it
+// vouches for is still cached. Network errors, challenges, 5xx and truncated pages still fail.
+fn load_html(url: &str, refresh: bool, held: bool) -> Result<Fetched> {
+    let key = response_cache_key(url);
+    let cached = if refresh { None } else { cache::get(&key, RESPONSE_CACHE_TTL) };
+    if let Some(doc) = cached.and_then(|body| Html::parse_with_url(&body, url).ok()) { return Ok(Fetched::Page(doc)); }
+
+    // Revalidate with the validators the server sent last time; a 304 reuses the stale body, or
+    // tells a `held` caller to keep what it has. Validators sit in their own cache index, so they
+    // outlive the bodies a library refresh pushes out.
+    let validators_key = format!("validators.{}", url);
+    let validators = cache::get(&validators_key, i64::MAX);
+    let stale = cache::get(&key, i64::MAX);
+    let mut headers = Vec::new();
+    if let Some(validators) = validators.as_deref().filter(|_| held || stale.is_some()) {
+        let (etag, modified) = validators.split_once('\n').unwrap_or((validators, ""));
+        if !etag.is_empty() { headers.push(("If-None-Match", etag)); }
+        if !modified.is_empty() { headers.push(("If-Modified-Since", modified)); }
+    }
+    let response = send_with_retry(url, retry_count(), &headers)?;
+    let status = response.status_code();
+    // Error pages (login prompts, 404s) would otherwise parse as an empty listing
+    if status >= 400 {
+        let body = response.get_string().unwrap_or_default();
+        if is_challenge(&body) { return MadokamiError::Challenge { url: url.into(), status }.fail(); }
+        let error = MadokamiError::Status { url: url.into(), status, target: Target::Page };
+        if status >= 500 { return error.fail(); }
+        return Ok(Fetched::Refused(error));
+    }
+    let body = match (status, stale, validators) {
+        (304, _, Some(validators)) if held => {
+            cache::set_validator(&validators_key, &validators);
+            return Ok(Fetched::NotModified);
+        }
+        (304, Some(body), Some(validators)) => {
+            cache::set(&key, &body);
+            cache::set_validator(&validators_key, &validators);
+            body
+        }
+        _ => {
+            let etag = response.get_header("ETag").unwrap_or_default();
+            let modified = response.get_header("Last-Modified").unwrap_or_default();
+            let mut body = sanitize_html(response.get_string()?);
+            if is_challenge(&body) { return MadokamiError::Challenge { url: url.into(), status }.fail(); }
+            // One fresh attempt for a cut-off body before reporting it; never cache a partial page
+            if is_truncated(&body) && retry_count() > 0 {
+                body = sanitize_html(send_with_retry(url, 0, &[])?.get_string()?);
+            }
+            if is_truncated(&body) { return MadokamiError::Truncated { url: url.into() }.fail(); }
+            if status == 200 {
+                if !etag.is_empty() || !modified.is_empty() {
+                    cache::set_validator(&validators_key, &format!("{}\n{}", etag, modified));
+                }
+                cache::set(&key, &body);
+            }
+            body
+        }
+    };
+    parse_page(url, status, &body).map(Fetched::Page)
+}
+
+// Follow the reader page's mark-as-read link, if it has one. Failures are ignored: read sync is best-effort
+fn mark_read_on_site(reader: &Document) {
+    let href = reader.select("a.mark-read, [data-mark-read]")
+        .and_then(|els| els.first())
+        .and_then(|el| el.attr("data-mark-read").filter(|h| !h.is_empty()).or_else(|| el.attr("href")));
+    if let Some(href) = href.filter(|h| h.starts_with('/')) {
+        if let Ok(req) = auth_get(&MadokamiUrl::new().path(&href).build()) { let _ = req.send(); }
+    }
+}
+
+// =================================================================================
+// READER
+// =================================================================================
+// One archive shown by the reader: its data-path and decoded data-files list
+type ReaderArchive = (String, Vec<String>);
+
+// Every div#reader on a reader page; some pages concatenate several archives
+fn reader_files(html: &Document) -> Vec<ReaderArchive> {
+    selectors::READER.all(html).into_iter().filter_map(|el| {
+        let data_path = el.attr("data-path").unwrap_or_default();
+        let files_json = el.attr("data-files").unwrap_or_default();
+        if data_path.is_empty() || files_json.is_empty() { return None; }
+        Some((data_path, decode_file_list(&files_json)))
+    }).collect()
+}
+
+// Reader href continuing the current chapter in another archive, if the page links one
+fn reader_continuation(html: &Document) -> Option<String> {
+    html.select("a.next-archive, a[rel='next'][href^='/reader'], link[rel='next'][href^='/reader']")
+        .and_then(|els| els.first())
+        .and_then(|el| el.attr("href"))
+        .filter(|h| h.starts_with('/'))
+}
+
+// Decode a data-files payload: a JSON string array, possibly HTML-entity-escaped or base64-encoded
+fn decode_file_list(raw: &str) -> Vec<String> {
+    let raw = raw.trim();
+    // Some pages double-escape the attribute, leaving `[&quot;001.jpg&quot;]` after parsing
+    let unescaped;
+    let raw = if raw.contains('&') { unescaped = unescape_html(raw); unescaped.as_str() } else { raw };
+    if raw.starts_with('[') {
+        return parse_string_array(raw).unwrap_or_default();
+    }
+    // Non-JSON payloads are base64 of the same array
+    util::base64_decode(raw)
+        .and_then(|bytes| String::from_utf8(bytes).ok())
+        .and_then(|json| parse_string_array(json.trim()))
+        .unwrap_or_default()
+}
+
+// Parse a flat JSON array of strings (`["001.jpg", "002.jpg"]`); anything else is rejected
+fn parse_string_array(json: &str) -> Option<Vec<String>> {
+    let mut chars = json.chars().peekable();
+    let skip_ws = |chars: &mut core::iter::Peekable<core::str::Chars>| {
+        while chars.next_if(|c| c.is_whitespace()).is_some() {}
+    };
+    skip_ws(&mut chars);
+    if chars.next()? != '[' { return None; }
+    let mut items = Vec::new();
+    skip_ws(&mut chars);
+    if chars.next_if_eq(&']').is_some() {
+        skip_ws(&mut chars);
+        return chars.next().is_none().then_some(items);
+    }
+    loop {
+        skip_ws(&mut chars);
+        if chars.next()? != '"' { return None; }
+        let mut item = String::new();
+        loop {
+            match chars.next()? {
+                '"' => break,
+                '\\' => match chars.next()? {
+                    '"' => item.push('"'),
+                    '\\' => item.push('\\'),
+                    '/' => item.push('/'),
+                    'b' => item.push('\u{8}'),
+                    'f' => item.push('\u{c}'),
+                    'n' => item.push('\n'),
+                    'r' => item.push('\r'),
+                    't' => item.push('\t'),
+                    'u' => {
+                        let high = utf16_unit(&mut chars)?;
+                        let code = if (0xD800..0xDC00).contains(&high) {
+                            if chars.next()? != '\\' || chars.next()? != 'u' { return None; }
+                            let low = utf16_unit(&mut chars)?;
+                            if !(0xDC00..0xE000).contains(&low) { return None; }
+                            0x10000 + ((high - 0xD800) << 10) + (low - 0xDC00)
+                        } else { high };
+                        item.push(char::from_u32(code)?);
+                    }
+                    _ => return None,
+                },
+                c => item.push(c),
+            }
+        }
+        items.push(item);
+        skip_ws(&mut chars);
+        match chars.next()? {
+            ',' => continue,
+            ']' => break,
+            _ => return None,
+        }
+    }
+    skip_ws(&mut chars);
+    chars.next().is_none().then_some(items)
+}
+
+// The four hex digits of a JSON `\uXXXX` escape
+fn utf16_unit(chars: &mut impl Iterator<Item = char>) -> Option<u32> {
+    let hex = chars.take(4).collect::<String>();
+    if hex.len() != 4 { return None; }
+    u32::from_str_radix(&hex, 16).ok()
+}
+
+// Fetch a reader page and decode its archives (following continuation links), returning the
+// session cookie it set (if any)
+fn fetch_reader_files(url: &str, chapter_key: &str, sync_read: bool) -> Result<(Vec<ReaderArchive>, Option<String>)> {
+    let response = send_with_retry(url, retry_count(), &[])?;
+    // Session cookie set by the reader page; image requests replay it alongside the Referer
+    let cookie = response.get_header("Set-Cookie")
+        .and_then(|c| c.split(';').next().map(|c| c.trim().to_string()))
+        .filter(|c| !c.is_empty());
+    let status = response.status_code();
+    let body = sanitize_html(response.get_string()?);
+    if is_challenge(&body) { return MadokamiError::Challenge { url: url.into(), status }.fail(); }
+    let html = parse_page(url, status, &body)?;
+    if sync_read {
+        mark_read_on_site(&html);
+    }
+    let mut archives = reader_files(&html);
+    if archives.is_empty() { archives.extend(reader_files_fallback(chapter_key)); }
+    if archives.is_empty() { return empty_reader_error(url, status, &html).fail(); }
+    let mut next = reader_continuation(&html);
+    let mut parts = 1;
+    while let Some(href) = next.take() {
+        if parts >= MAX_ARCHIVE_PARTS { break; }
+        // A missing part would silently drop its pages
+        let part = fetch_html(&MadokamiUrl::new().path(&href).build())?;
+        let more = reader_files(&part);
+        // Stop on loops back to an archive already collected
+        if more.is_empty() || more.iter().any(|m| archives.iter().any(|a| a.0 == m.0)) { break; }
+        archives.extend(more);
+        next = reader_continuation(&part);
+        parts += 1;
+    }
+    Ok((archives, cookie))
+}
+
+// Reader metadata cache: "reader.<chapter key>" ->
+// "<stored at>\n[=<session cookie>\n]><data-path>\n<file>\n<file>\n><data-path>..."
+// The reader page's session cookie is kept with the archives so cached opens still send it.
+fn reader_cache_key(chapter_key: &str) -> String {
+    format!("reader.{}", chapter_key)
+}
+
+fn cached_reader_files(chapter_key: &str) -> Option<(Vec<ReaderArchive>, Option<String>)> {
+    let raw = cache::get(&reader_cache_key(chapter_key), READER_CACHE_TTL)?;
+    let mut lines = raw.split('\n').peekable();
+    let cookie = lines.next_if(|line| line.starts_with('=')).and_then(|line| line.strip_prefix('=')).map(String::from);
+    let mut archives: Vec<ReaderArchive> = Vec::new();
+    for line in lines {
+        match (line.strip_prefix('>'), archives.last_mut()) {
+            (Some(path), _) => archives.push((path.to_string(), Vec::new())),
+            (None, Some((_, files))) => files.push(line.to_string()),
+            (None, None) => return None,
+        }
+    }
+    if archives.iter().any(|(_, files)| files.is_empty()) || archives.is_empty() { None } else { Some((archives, cookie)) }
+}
+
+fn store_reader_files(chapter_key: &str, archives: &[ReaderArchive], cookie: Option<&str>) {
+    let mut raw = String::new();
+    if let Some(cookie) = cookie.filter(|c| !c.contains('\n')) {
+        raw.push('=');
+        raw.push_str(cookie);
+    }
+    for (data_path, files) in archives {
+        if !raw.is_empty() { raw.push('\n'); }
+        raw.push('>');
+        raw.push_str(data_path);
+        for file in files {
+            raw.push('\n');
+            raw.push_str(file);
+        }
+    }
+    cache::set(&reader_cache_key(chapter_key), &raw);
+}
+
+// HEAD the first page so auth/permission problems fail fast instead of as a reader full of broken images
+fn validate_page_url(page_url: &str, referer: &str) -> Result<()> {
+    let mut req = Request::head(page_url)?;
+    authorize(&mut req);
+    req.set_header("Referer", referer);
+    let status = req.send()?.status_code();
+    match status {
+        401 | 403 | 404 | 410 => MadokamiError::Status { url: page_url.into(), status, target: Target::Images }.fail(),
+        _ => Ok(()),
+    }
+}
+
+// Explain why a reader page produced no pages so users know whether to retry, log in or report
+fn empty_reader_error(url: &str, status: i32, html: &Document) -> MadokamiError {
+    if status >= 400 { return MadokamiError::Status { url: url.into(), status, target: Target::Reader }; }
+    let has_reader = selectors::READER.first(html).is_some();
+    MadokamiError::Parse {
+        url: url.into(),
+        status: Some(status),
+        missing: (!has_reader).then_some(selectors::READER.name),
+        reason: if has_reader {
+            "The reader returned no pages for this file; the archive may be empty or damaged"
+        } else {
+            "Madokami hasn't extracted this archive for the reader yet; try again later or open it on the website"
+        },
+    }
+}
+
+// Archive path of a reader key ("/reader/Manga/A/AB/Title/x.zip" -> "/Manga/A/AB/Title/x.zip")
+fn reader_data_path(chapter_key: &str) -> String {
+    let path = chapter_key.strip_prefix("/reader").unwrap_or(chapter_key);
+    percent_decode(path.split(['?', '#']).next().unwrap_or(path))
+}
+
+// Unprocessed archives render no div#reader; ask the archive listing endpoint for the file list directly
+fn reader_files_fallback(chapter_key: &str) -> Option<ReaderArchive> {
+    let data_path = reader_data_path(chapter_key);
+    let url = MadokamiUrl::new().path("/reader/files").query("path", &data_path).build();
+    let body = auth_get(&url).ok()?.string().ok()?;
+    let files = decode_file_list(&body);
+    if files.is_empty() { None } else { Some((data_path, files)) }
+}
+
+// `params` are the extra query parameters from image_params()
+fn reader_image_url(data_path: &str, file: &str, params: &[(&str, String)]) -> String {
+    let mut url = MadokamiUrl::new()
+        .path("/reader/image")
+        .query_path("path", data_path)
+        .query_path("file", file.trim_start_matches('/'));
+    for (name, value) in params { url = url.query(name, value); }
+    url.build()
+}
+
+// Extra image endpoint parameters from the reader settings ("data saver" resizes server-side,
+// "original" asks for the unresized scan)
+fn image_params() -> Vec<(&'static str, String)> {
+    // Original resolution wins over data saver
+    if defaults_get::<bool>("originalImages").unwrap_or(false) {
+        return alloc::vec![("raw", "1".into())];
+    }
+    defaults_get::<String>("dataSaverWidth")
+        .and_then(|w| w.parse::<u32>().ok())
+        .filter(|w| *w > 0)
+        .map(|width| alloc::vec![("width", width.to_string())])
+        .unwrap_or_default()
+}
+
+// Direct link to the original file behind a reader key
+fn download_url(chapter_key: &str) -> String {
+    MadokamiUrl::new().file_path(&reader_data_path(chapter_key)).build()
+}
+
+// Single text page for PDF/EPUB files pointing at the direct download
+fn document_page(chapter_key: &str, ext: &str) -> Page {
+    let download = download_url(chapter_key);
+    let text = format!(
+        "This chapter is a {} file, which can't be shown as images.\n\nDownload it directly: {}",
+        ext.to_ascii_uppercase(),
+        download
+    );
+    Page { content: PageContent::Text(text), ..Default::default() }
+}
+
+// Novel files are fetched directly and split into reader-sized text pages at paragraph breaks
+#[cfg(feature = "novels")]
+fn novel_pages(data_path: &str) -> Result<Vec<Page>> {
+    let url = MadokamiUrl::new().file_path(data_path).build();
+    let response = send_with_retry(&url, retry_count(), &[])?;
+    let status = response.status_code();
+    // An error page would otherwise be shown as the chapter's text
+    if status >= 400 { return MadokamiError::Status { url, status, target: Target::Reader }.fail(); }
+    let body = response.get_string()?.replace("\r\n", "\n");
+    let text = if matches!(extension(data_path).as_deref(), Some("html" | "htm")) { html_to_text(&body) } else { body };
+    let mut pages = Vec::new();
+    let mut current = String::new();
+    for (piece, new_paragraph) in novel_pieces(&text) {
+        if !current.is_empty() && current.len() + piece.len() > NOVEL_PAGE_CHARS {
+            pages.push(Page { content: PageContent::Text(core::mem::take(&mut current)), ..Default::default() });
+        }
+        if !current.is_empty() { current.push_str(if new_paragraph { "\n\n" } else { "\n" }); }
+        current.push_str(piece);
+    }
+    if !current.is_empty() {
+        pages.push(Page { content: PageContent::Text(current), ..Default::default() });
+    }
+    Ok(pages)
+}
+
+// Text pieces no longer than a page, flagged when they start a paragraph. Paragraphs over the
+// limit (or files using single newlines only) break at lines, and run-on lines at the last space.
+#[cfg(feature = "novels")]
+fn novel_pieces(text: &str) -> Vec<(&str, bool)> {
+    let mut pieces = Vec::new();
+    for paragraph in text.split("\n\n").map(str::trim).filter(|p| !p.is_empty()) {
+        let mut new_paragraph = true;
+        let lines = if paragraph.len() > NOVEL_PAGE_CHARS { paragraph.split('\n').collect() } else { alloc::vec![paragraph] };
+        for mut line in lines.into_iter().map(str::trim).filter(|l| !l.is_empty()) {
+            while line.len() > NOVEL_PAGE_CHARS {
+                let mut cut = NOVEL_PAGE_CHARS;
+                while !line.is_char_boundary(cut) { cut -= 1; }
+                if let Some(space) = line[..cut].rfind(' ').filter(|&s| s > 0) { cut = space; }
+                pieces.push((line[..cut].trim_end(), core::mem::take(&mut new_paragraph)));
+                line = line[cut..].trim_start();
+            }
+            if !line.is_empty() { pieces.push((line, core::mem::take(&mut new_paragraph))); }
+        }
+    }
+    pieces
+}
+
+// Crude markup stripper for novel chapters: block tags become paragraph breaks, other tags vanish
+#[cfg(feature = "novels")]
+fn html_to_text(html: &str) -> String {
+    let mut out = String::with_capacity(html.len());
+    let mut rest = html;
+    while let Some(open) = rest.find('<') {
+        out.push_str(&rest[..open]);
+        let Some(close) = rest[open..].find('>').map(|c| open + c) else { rest = ""; break };
+        let tag = rest.get(open + 1..close).unwrap_or_default().trim_start_matches('/').to_ascii_lowercase();
+        if ["p", "br", "div", "h1", "h2", "h3", "li"].iter().any(|t| tag == *t || tag.starts_with(&format!("{t} "))) {
+            out.push_str("\n\n");
+        }
+        rest = rest.get(close + 1..).unwrap_or_default();
+    }
+    out.push_str(rest);
+    unescape_html(&out)
+}
+
+// Chapter thumbnails point at the reader page with this marker; the image request hook
+// resolves them to the first page only when the app actually displays one.
+const THUMBNAIL_MARKER: &str = "#thumbnail";
+// Key suffix of the continuation placeholder closing a capped chapter list
+const MORE_CHAPTERS_MARKER: &str = "#more";
+
+fn chapter_thumbnail_request(url: &str) -> Result<Request> {
+    let reader_url = url.trim_end_matches(THUMBNAIL_MARKER);
+    let html = auth_get(reader_url)?.html()?;
+    let first_page = reader_files(&html).into_iter()
+        .find_map(|(path, files)| files.first().map(|f| reader_image_url(&path, f, &image_params())));
+    match first_page {
+        Some(first_page) => auth_get(&first_page),
+        None => MadokamiError::Parse {
+            url: reader_url.into(),
+            status: None,
+            missing: Some(selectors::READER.name),
+            reason: "No pages found for chapter thumbnail",
+        }.fail(),
+    }
+}
+
+// =================================================================================
+// CHAPTER INDEX
+// =================================================================================
+// Resolve a pagination href ("?page=2", "/Manga/..?page=2" or absolute) against the directory it came from
+fn resolve_index_href(href: &str, dir: &str) -> String {
+    if href.starts_with("http://") || href.starts_with("https://") { href.to_string() }
+    else if href.starts_with('?') { MadokamiUrl::new().path(dir).query_string(href).build() }
+    else { MadokamiUrl::new().path(href).build() }
+}
+
+// Parse a directory index and every following page of it (large series paginate their file table)
+// A page that fails to load fails the whole listing: a partial list would replace the stored one
+fn parse_index_pages(first: &Document, dir: &str, scan: &mut IndexScan) -> Result<(Vec<Chapter>, Vec<String>)> {
+    let next_href = |doc: &Document| selectors::NEXT_PAGE.first(doc)
+        .and_then(|a| a.attr("href"))
+        .filter(|h| !h.is_empty() && h != "#");
+    // Pages run oldest to newest, so the newest files are on the last one. The next links are
+    // walked to find it, keeping only each page's URL; pages are then parsed from the last one
+    // back (reloaded from the response cache) until the chapter cap is filled.
+    let mut urls = Vec::new();
+    let mut next = next_href(first);
+    while let Some(href) = next.take() {
+        if urls.len() + 1 >= MAX_INDEX_PAGES { break; }
+        let url = resolve_index_href(&href, dir);
+        next = next_href(&cached_html(&url, true)?);
+        urls.push(url);
+    }
+    scan.extra_pages += urls.len();
+    let (mut chapters, mut subdirs) = (Vec::new(), Vec::new());
+    let mut push = |(page_chapters, page_dirs): (Vec<Chapter>, Vec<String>)| {
+        chapters.push(page_chapters);
+        subdirs.push(page_dirs);
+    };
+    for url in urls.iter().rev() {
+        if scan.remaining == 0 { scan.truncated = true; break; }
+        push(parse_index_table(&cached_html(url, false)?, dir, scan));
+    }
+    if scan.remaining > 0 { push(parse_index_table(first, dir, scan)); } else { scan.truncated = true; }
+    // Back to table order
+    Ok((chapters.into_iter().rev().flatten().collect(), subdirs.into_iter().rev().flatten().collect()))
+}
+
+// "Vol. 2 Ch. 13.5 — Group" (or "Ch. 1-10" for a range archive) from parsed filename parts;
+// None when nothing was parsed
+fn render_chapter_title(volume: Option<f32>, chapter: Option<f32>, chapter_end: Option<f32>, scanlators: Option<&[String]>) -> Option<String> {
+    let mut out = String::new();
+    if let Some(v) = volume { let _ = write!(out, "Vol. {}", v); }
+    if let Some(c) = chapter {
+        if !out.is_empty() { out.push(' '); }
+        let _ = write!(out, "Ch. {}", c);
+        if let Some(end) = chapter_end { let _ = write!(out, "-{}", end); }
+    }
+    if out.is_empty() { return None; }
+    if let Some(groups) = scanlators.filter(|g| !g.is_empty()) {
+        let _ = write!(out, " — {}", groups.join(", "));
+    }
+    Some(out)
+}
+
+// 1-based column positions in an index table
+struct IndexColumns {
+    name: usize,
+    size: Option<usize>,
+    date: Option<usize>,
+    uploader: Option<usize>,
+}
+
+// Locate the file table (desktop `#index-table`, falling back to the `mobile-files-table` layout)
+// and detect its columns from the header, defaulting to the desktop order Name | Size | Date | Uploader.
+fn index_rows(html: &Document) -> Option<(ElementList, IndexColumns)> {
+    let chain = &selectors::INDEX_TABLE;
+    for (i, table_sel) in chain.selectors.iter().enumerate() {
+        let Some(table) = html.select_first(table_sel) else { continue };
+        let Some(rows) = table.select("tbody > tr") else { continue };
+        chain.record(i);
+        let mut cols = IndexColumns { name: 1, size: Some(2), date: Some(3), uploader: Some(4) };
+        if let Some(headers) = table.select("thead th") {
+            let (mut size, mut date, mut uploader) = (None, None, None);
+            for (i, th) in headers.enumerate() {
+                let label = th.text().unwrap_or_default().to_ascii_lowercase();
+                if label.contains("name") || label.contains("file") { cols.name = i + 1; }
+                else if label.contains("size") { size = Some(i + 1); }
+                else if label.contains("uploader") || label == "by" || label.contains("user") { uploader = Some(i + 1); }
+                else if label.contains("date") || label.contains("added") || label.contains("uploaded") { date = Some(i + 1); }
+            }
+            if size.is_some() || date.is_some() || uploader.is_some() {
+                cols.size = size;
+                cols.date = date;
+                cols.uploader = uploader;
+            }
+        }
+        return Some((rows, cols));
+    }
+    None
+}
+
+// The index flags broken archives with a warning icon, a "danger" row or a corrupt/broken tag
+fn is_flagged_corrupt(row: &Element) -> bool {
+    if row.select_first("i.fa-exclamation-triangle, span.glyphicon-warning-sign, .corrupt, .broken").is_some() {
+        return true;
+    }
+    if row.attr("class").is_some_and(|c| c.split_whitespace().any(|c| c == "danger" || c == "corrupt")) {
+        return true;
+    }
+    row.select("span.tag, a.tag, .label")
+        .map(|tags| tags.filter_map(|t| t.text()).any(|t| {
+            let t = t.to_ascii_lowercase();
+            t.contains("corrupt") || t.contains("broken")
+        }))
+        .unwrap_or(false)
+}
+
+// State shared across every index page and subfolder of one series
+struct IndexScan {
+    // Chapter cap for mega-series, filled from the newest rows; older rows and pages past it are
+    // never read, only flagged as `truncated`
+    remaining: usize,
+    truncated: bool,
+    // Listing pages fetched past the first page of a folder
+    extra_pages: usize,
+}
+
+impl IndexScan {
+    fn new(manga_key: &str) -> Self {
+        let remaining = chapter_cap(manga_key).unwrap_or(usize::MAX);
+        Self { remaining, truncated: false, extra_pages: 0 }
+    }
+
+    // Placeholder listed after the oldest chapter of a capped list; opening it loads more
+    fn continuation(&self, manga_key: &str) -> Option<Chapter> {
+        if !self.truncated { return None; }
+        let title = "Older files not loaded; open this to list more".into();
+        Some(Chapter { key: format!("{manga_key}{MORE_CHAPTERS_MARKER}"), title: Some(title), ..Default::default() })
+    }
+}
+
+// Files listed for a series: the "chapterCap" setting plus whatever the series' continuation
+// placeholder added. None when the setting is unlimited.
+fn chapter_cap(manga_key: &str) -> Option<usize> {
+    let cap = defaults_get::<String>("chapterCap")
+        .and_then(|v| v.parse::<usize>().ok())
+        .filter(|n| *n > 0)?;
+    let extra = defaults_get::<String>(&format!("chapterCap.{manga_key}"))
+        .and_then(|v| v.parse::<usize>().ok())
+        .unwrap_or(0);
+    Some(cap.saturating_add(extra))
+}
+
+// Settings a built chapter list depends on, for deciding whether a stored one can be kept
+const CHAPTER_LIST_SETTINGS: [&str; 10] = [
+    "chapterTitleFormat", "fileSizeDisplay", "archiveKind", "releasePreference", "corruptedArchives",
+    "chapterOrder", "preferredFormat", "serverTimezone", "chapterLinkSelector", "username",
+];
+const CHAPTER_LIST_TOGGLES: [&str; 2] = ["chapterThumbnails", "syncReadMarkers"];
+
+// Recorded when a series' chapters came from its folder page alone (no subfolders, later pages
+// or placeholder), so a 304 for that page means the stored list is still complete
+fn listing_key(manga_key: &str) -> String {
+    format!("listing.{}", manga_key)
+}
+
+fn chapter_list_fingerprint(manga_key: &str) -> String {
+    let mut out = format!("{:?}", chapter_cap(manga_key));
+    for key in CHAPTER_LIST_SETTINGS {
+        out.push('\n');
+        out.push_str(&defaults_get::<String>(key).unwrap_or_default());
+    }
+    for key in CHAPTER_LIST_TOGGLES {
+        out.push_str(if defaults_get::<bool>(key).unwrap_or(false) { "\n1" } else { "\n0" });
+    }
+    out
+}
+
+// Stand-in for a subfolder the account isn't allowed to list
+fn locked_folder(dir: &str) -> Chapter {
+    let name = dir.rsplit('/').next().map(percent_decode).unwrap_or_default();
+    let title = format!("{name}/ (locked: account lacks access)");
+    Chapter { key: dir.into(), title: Some(title), url: Some(MadokamiUrl::new().path(dir).build()), locked: true, ..Default::default() }
+}
+
+// Opened continuation placeholder: raise the series' cap by another "chapterCap" worth of files
+// and explain that the next refresh lists them
+fn load_more_chapters(manga_key: &str) -> Page {
+    let text: String = match chapter_cap(manga_key) {
+        // The series' extra allowance grows from (cap - step) to cap, i.e. by one step
+        Some(cap) => {
+            defaults_set(&format!("chapterCap.{manga_key}"), DefaultValue::String(cap.to_string()));
+            "More files will be listed: refresh the chapter list to load them.".into()
+        }
+        None => "The chapter limit is off: refresh the chapter list to load every file.".into(),
+    };
+    Page { content: PageContent::Text(text), ..Default::default() }
+}
+
+// Parse a directory index table into chapters (rows with a reader link, or locked files without one)
+// and subfolder paths (rows whose name link points below `dir` but have no reader link).
+fn parse_index_table(html: &Document, dir: &str, scan: &mut IndexScan) -> (Vec<Chapter>, Vec<String>) {
+    let mut chapters = Vec::new();
+    let mut subdirs = Vec::new();
+    let Some((rows, cols)) = index_rows(html) else { return (chapters, subdirs) };
+    let dir_prefix = format!("{}/", dir.trim_end_matches('/'));
+    let base = base_url();
+    let size_display = defaults_get::<String>("fileSizeDisplay").unwrap_or_default();
+    let title_format = defaults_get::<String>("chapterTitleFormat").unwrap_or_else(|| "tidy".into());
+    let sync_read = defaults_get::<bool>("syncReadMarkers").unwrap_or(false);
+    let skip_corrupted = defaults_get::<String>("corruptedArchives").as_deref() == Some("skip");
+    let thumbnails = defaults_get::<bool>("chapterThumbnails").unwrap_or(false);
+    let utc_offset = server_utc_offset();
+    let link_override = selectors::CHAPTER_LINK.user_selector();
+    let novels = dir.starts_with("/Novels");
+    let readable = |name: &str| is_readable_file(name) || (cfg!(feature = "novels") && novels && is_novel_file(name));
+    // Mega-series have thousands of rows: cells are collected once per row and indexed by column
+    // instead of running an nth-child query per field. Rows are oldest-first; they are read from
+    // the end so the chapter cap keeps the newest ones, and put back in table order at the end.
+    for row in rows.collect::<Vec<Element>>().into_iter().rev() {
+        // Once the cap is filled the older rows aren't read at all
+        if scan.remaining == 0 { scan.truncated = true; break; }
+        let cells = row.select("td").map(|c| c.collect::<Vec<Element>>()).unwrap_or_default();
+        let cell = |col: usize| cells.get(col.checked_sub(1)?);
+        let cell_text = |col: Option<usize>| col.and_then(cell).and_then(|c| c.text());
+        let name_link = cell(cols.name).and_then(|c| c.select_first("a"));
+        let name_href = name_link.as_ref().and_then(|a| a.attr("href"));
+        let title = name_link.as_ref().and_then(|a| a.text());
+        let reader_href = link_override.as_deref().and_then(|sel| row.select_first(sel))
+            .or_else(|| cells.get(5).and_then(|c| c.select_first("a")))
+            .or_else(|| row.select_first("a[href^='/reader']"))
+            .and_then(|a| a.attr("href"));
+        // Only a lock marker locks a row up front; a file the account can't open reports its 403 when opened
+        let locked = row.select_first("i.fa-lock, span.glyphicon-lock, .locked").is_some();
+        // Readability is decided here, before a row can become a chapter or a subdirectory
+        let href = match (reader_href, name_href) {
+            (Some(href), name) => {
+                if !name.as_deref().or(title.as_deref()).is_none_or(readable) { continue; }
+                href
+            }
+            // Documents and novels have no reader link and open from their file path; archives
+            // missing one go through the reader route for the file
+            (None, Some(href)) if extension(&href).is_some() => {
+                if !readable(&href) { continue; }
+                if opens_as_file(&href) { href } else { format!("/reader{}", normalize_chapter_href(&href)) }
+            }
+            // Only folders are walked; files without a reader link (.nfo, .jpg, ...) are never fetched as indexes
+            (None, Some(sub)) => {
+                if extension(&sub).is_none() && sub.starts_with(&dir_prefix) && sub.len() > dir_prefix.len() {
+                    subdirs.push(sub.trim_end_matches('/').to_string());
+                }
+                continue;
+            }
+            (None, None) => continue,
+        };
+        let key = normalize_chapter_href(&href);
+        let date_raw = cell_text(cols.date).unwrap_or_default();
+        let date_uploaded = date::parse_chapter_date(&date_raw, utc_offset);
+        let corrupted = is_flagged_corrupt(&row);
+        if corrupted && skip_corrupted { continue; }
+        let chapter_number = title.as_deref().and_then(parse_chapter_number);
+        let volume_number = title.as_deref().and_then(parse_volume_number);
+        let chapter_end = title.as_deref().and_then(parse_chapter_range)
+            .filter(|(start, _)| Some(*start) == chapter_number)
+            .map(|(_, end)| end);
+        // Uploader stands in for the group when the filename carries none
+        let mut scanlators = title.as_deref().and_then(parse_scanlators).or_else(|| {
+            cell_text(cols.uploader)
+                .map(|u| u.trim().to_string())
+                .filter(|u| !u.is_empty())
+                .map(|u| alloc::vec![u])
+        });
+        let release = title.as_deref().and_then(release_kind);
+        // "Filename" shows the name exactly as listed and the default tidies it; the clean format
+        // falls back to a tidied filename when nothing could be parsed
+        let mut title = match title_format.as_str() {
+            "raw" => title,
+            "clean" => match render_chapter_title(volume_number, chapter_number, chapter_end, scanlators.as_deref()) {
+                Some(mut clean) => {
+                    if let Some(kind) = release { let _ = write!(clean, " ({})", kind.label()); }
+                    Some(clean)
+                }
+                None => title.map(|t| clean_title(&t)),
+            },
+            _ => title.map(|t| clean_title(&t)),
+        };
+        // File size helps pick between duplicate releases
+        if matches!(size_display.as_str(), "title" | "scanlator") {
+            if let Some(size) = cell_text(cols.size).filter(|s| !s.trim().is_empty()) {
+                let size = size.trim();
+                match size_display.as_str() {
+                    "title" => if let Some(t) = title.as_mut() { let _ = write!(t, " ({size})"); },
+                    "scanlator" => scanlators.get_or_insert_with(Vec::new).push(size.to_string()),
+                    _ => {}
+                }
+            }
+        }
+        if let Some(t) = title.as_mut() {
+            if locked { t.push_str(" (locked: account lacks access)"); }
+            if corrupted { t.push_str(" (corrupted)"); }
+            // Aidoku has no source-side read state, so site read markers are surfaced in the title
+            if sync_read && row.select_first("i.fa-check, .read-marker, td.read").is_some() {
+                t.push_str(" (read)");
+            }
+        }
+        let url_full = MadokamiUrl::with_base(&base).path(&key).build();
+        // Reader thumbnails only exist for image archives
+        let thumbnail = (thumbnails && !locked && !opens_as_file(&key)).then(|| format!("{url_full}{THUMBNAIL_MARKER}"));
+        scan.remaining -= 1;
+        chapters.push(Chapter { key, title, chapter_number, volume_number, scanlators, date_uploaded, url: Some(url_full), thumbnail, locked, ..Default::default() });
+    }
+    chapters.reverse();
+    subdirs.reverse();
+    (chapters, subdirs)
+}
+
+// Files shown without the image reader: documents get a download page, novels become text pages
+fn opens_as_file(path: &str) -> bool {
+    matches!(extension(path).as_deref(), Some("pdf" | "epub")) || is_novel_file(path)
+}
+
+// Unnumbered extras/omake (in ascending table order) slot in right after the preceding
+// numbered chapter: 10 -> 10.5, a second extra -> 10.6, ... capped below the next integer.
+// Volume archives ("Title v03 Extras.zip") keep sorting by volume instead.
+fn number_extras(chapters: &mut [Chapter]) {
+    let mut last = 0.0_f32;
+    let mut step = 0;
+    for chapter in chapters.iter_mut() {
+        match chapter.chapter_number {
+            Some(n) => { last = n; step = 0; }
+            _ if chapter.volume_number.is_none() && chapter.title.as_deref().is_some_and(is_extra) => {
+                let offset = (0.5 + 0.1 * step as f32).min(0.9);
+                chapter.chapter_number = Some(last.floor() + offset);
+                step += 1;
+            }
+            _ => {}
+        }
+    }
+}
+
+// Newest-first ordering independent of the site's table order: chapter number, then volume,
+// then natural filename order. Unknown numbers sort lowest (volume archives before chapters).
+fn sort_chapters(chapters: &mut [Chapter]) {
+    let number = |n: Option<f32>| n.unwrap_or(f32::NEG_INFINITY);
+    chapters.sort_by(|a, b| {
+        number(b.chapter_number).total_cmp(&number(a.chapter_number))
+            .then_with(|| number(b.volume_number).total_cmp(&number(a.volume_number)))
+            .then_with(|| natural_cmp(b.title.as_deref().unwrap_or(&b.key), a.title.as_deref().unwrap_or(&a.key)))
+    });
+}
+
+// When a series has both whole-volume archives and per-chapter files, drop the duplicated kind
+// per the "archiveKind" setting. Only content known to be covered by the other kind is removed:
+// chapters whose volume exists as an archive, or volumes whose chapters exist as files.
+fn filter_archive_kind(chapters: &mut Vec<Chapter>) {
+    let is_volume_archive = |c: &Chapter| c.volume_number.is_some() && c.chapter_number.is_none();
+    let volume_of = |c: &Chapter| c.volume_number.map(f32::to_bits);
+    match defaults_get::<String>("archiveKind").as_deref() {
+        Some("volumes") => {
+            let volumes: Vec<u32> = chapters.iter().filter(|c| is_volume_archive(c)).filter_map(volume_of).collect();
+            chapters.retain(|c| is_volume_archive(c) || volume_of(c).is_none_or(|v| !volumes.contains(&v)));
+        }
+        Some("chapters") => {
+            let covered: Vec<u32> = chapters.iter().filter(|c| !is_volume_archive(c)).filter_map(volume_of).collect();
+            chapters.retain(|c| !is_volume_archive(c) || volume_of(c).is_none_or(|v| !covered.contains(&v)));
+        }
+        _ => {}
+    }
+}
+
+// With a "releasePreference" of digital or scan, drop releases of the other kind when the same
+// volume/chapter is also available in the preferred one. Unmarked releases are always kept.
+fn filter_release_kind(chapters: &mut Vec<Chapter>) {
+    let preferred = match defaults_get::<String>("releasePreference").as_deref() {
+        Some("digital") => ReleaseKind::Digital,
+        Some("scan") => ReleaseKind::Scan,
+        _ => return,
+    };
+    // Only the file name counts: a series folder like "Berserk - Colored (Digital)" tags every file
+    let kind_of = |c: &Chapter| release_kind(&percent_decode(c.key.rsplit('/').next().unwrap_or(&c.key)));
+    let slot = |c: &Chapter| (c.volume_number.map(f32::to_bits), c.chapter_number.map(f32::to_bits));
+    let available: BTreeSet<(Option<u32>, Option<u32>)> = chapters.iter()
+        .filter(|c| kind_of(c) == Some(preferred))
+        .map(slot)
+        .filter(|s| *s != (None, None))
+        .collect();
+    chapters.retain(|c| match kind_of(c) {
+        Some(kind) if kind != preferred => !available.contains(&slot(c)),
+        _ => true,
+    });
+}
+
+// Collapse the same release uploaded in several archive formats (x.zip + x.rar, x.cbz + x.cbr)
+// into one chapter, keeping the format family chosen in settings. Table order is preserved.
+fn dedup_formats(chapters: Vec<Chapter>) -> Vec<Chapter> {
+    let prefer_rar = defaults_get::<String>("preferredFormat").as_deref() == Some("rar");
+    let rank = |key: &str| -> u8 {
+        let is_rar = matches!(extension(key).as_deref(), Some("rar" | "cbr"));
+        if is_rar == prefer_rar { 0 } else { 1 }
+    };
+    let mut seen: BTreeMap<String, usize> = BTreeMap::new();
+    let mut out: Vec<Chapter> = Vec::with_capacity(chapters.len());
+    for chapter in chapters {
+        let stem = strip_extension(&chapter.key).to_ascii_lowercase();
+        match seen.get(&stem) {
+            Some(&idx) => {
+                if let Some(kept) = out.get_mut(idx).filter(|kept| rank(&chapter.key) < rank(&kept.key)) { *kept = chapter; }
+            }
+            None => {
+                seen.insert(stem, out.len());
+                out.push(chapter);
+            }
+        }
+    }
+    out
 }
 
 // =================================================================================
@@ -147,151 +1425,181 @@ impl Source for Madokami {
         _filters: Vec<FilterValue>,
     ) -> Result<MangaPageResult> {
     let q = query.unwrap_or_default();
-    // Site uses '+' for spaces in query (e.g., the+world+god+only+knows)
-    let plus_query = q.split_whitespace().collect::<Vec<&str>>().join("+");
-    let url = format!("{BASE_URL}/search?q={}", plus_query);
-        let html = auth_get(&url)?.html()?;
-        let entries = html
-            .select("div.container table tbody tr")
-            .map(|rows| {
-                rows.filter_map(|row| {
-                    let link = row.select_first("td:nth-child(1) a:nth-child(1)")?;
-                    let key = link.attr("href")?;
-                    let (title, description) = derive_from_path(&key);
-                    if title.is_empty() { return None; }
-                    Some(Manga { key, title, description: description.filter(|d| !d.is_empty()), ..Default::default() })
-                })
-                .collect::<Vec<Manga>>()
-            })
-            .unwrap_or_default();
-
-        Ok(MangaPageResult { entries, has_next_page: false })
+    let q = q.split_whitespace().collect::<Vec<&str>>().join(" ");
+    let url = MadokamiUrl::new().path("/search").query("q", &q).build();
+        let html = cached_html(&url, false)?;
+        Ok(MangaPageResult { entries: visible_entries(search_entries(&html)), has_next_page: false })
     }
 
     fn get_manga_update(&self, mut manga: Manga, needs_details: bool, needs_chapters: bool) -> Result<Manga> {
-        // Canonicalize key similar to Tachiyomi logic for /Manga/ and /Raws/ variants
-    // Canonicalization may adjust manga.key but we don't need to track if it changed.
-        if manga.key.starts_with("/Manga/") {
-            // Pattern: /Manga/<Initial>/<Title>/... remove extra segments beyond 5
-            // Split and if length > 6 ("", "Manga", initial, title, maybe extra) trim to first 5 segments
-            let parts: Vec<&str> = manga.key.split('/').collect();
-            if parts.len() > 6 && parts.get(2).map(|s| s.len() == 1).unwrap_or(false) {
-                // Keep: "", "Manga", initial, title, next two segments (index 0..=5)
-                let canon = parts[0..=5].join("/");
-                manga.key = if canon.starts_with('/') { canon } else { format!("/{}", canon) };
-            }
-        } else if manga.key.starts_with("/Raws/") {
-            // Trim trailing segments starting with '!' while remaining length >= 3
-            let mut parts: Vec<&str> = manga.key.split('/').collect();
-            let mut changed_local = false;
-            while parts.len() > 3 {
-                if let Some(last) = parts.last() { if last.starts_with('!') { parts.pop(); changed_local = true; continue; } }
-                break;
-            }
-            if changed_local {
-                let canon = parts.join("/");
-                manga.key = if canon.starts_with('/') { canon } else { format!("/{}", canon) };
-            }
-        }
+        manga.key = canonical_series_key(&manga.key);
 
-        let url = format!("{BASE_URL}{}", manga.key);
-        let html = auth_get(&url)?.html()?;
+        let url = MadokamiUrl::new().path(&manga.key).build();
+        manga.url = Some(url.clone());
+        if !needs_details && !needs_chapters { return Ok(manga); }
+        // Chapter refreshes must see new uploads; details alone can come from the cache.
+        // Library refreshes usually ask for chapters only, and then the details pass is skipped.
+        // A stored list built from this page alone, under the current settings, survives a 304.
+        let held = needs_chapters && !needs_details
+            && manga.chapters.as_deref().is_some_and(|c| !c.is_empty())
+            && cache::get(&listing_key(&manga.key), i64::MAX) == Some(chapter_list_fingerprint(&manga.key));
+        let listing = load_html(&url, needs_chapters, held)?;
+        // A renamed folder redirects; point the library entry at its new home so it isn't stranded
+        let moved = moved_key(&url).map(|key| canonical_series_key(&key))
+            .filter(|key| *key != manga.key && is_series_key(key));
+        if let Some(key) = moved.clone() {
+            manga.url = Some(MadokamiUrl::new().path(&key).build());
+            manga.key = key;
+        }
+        let html = match listing {
+            Fetched::Page(html) => html,
+            Fetched::Refused(error) => return error.fail(),
+            // Unchanged since the stored chapters were built: keep them
+            Fetched::NotModified if moved.is_none() => {
+                manga.next_update_time = manga.chapters.as_deref().and_then(estimate_next_update);
+                return Ok(manga);
+            }
+            // ...unless the folder moved, leaving their keys on the old path
+            Fetched::NotModified => cached_html(&url, true)?,
+        };
 
         if needs_details {
-            manga.cover = html.select("div.manga-info img[itemprop='image']")
-                .and_then(|els| els.first())
-                .and_then(|el| el.attr("src"));
-            // Re-derive title/description from key if not already set
-            if manga.title.is_empty() {
-                let (title, desc) = derive_from_path(&manga.key);
-                if !title.is_empty() { manga.title = title; }
-                if manga.description.is_none() { manga.description = desc; }
-            }
-            if let Some(title_override) = html.select("div.manga-info-title h1").and_then(|el| el.text()) {
-                if !title_override.is_empty() { manga.title = title_override; }
-            }
-            manga.authors = html.select("a[itemprop='author']").map(|els| {
-                els.filter_map(|e| e.text()).collect::<Vec<String>>()
-            });
-            manga.artists = html.select("a[itemprop='artist']").map(|els| {
-                els.filter_map(|e| e.text()).collect::<Vec<String>>()
-            });
-            manga.description = html
-                .select("div.manga-info-synopsis")
-                .and_then(|el| el.text());
-            let status_text = html
-                .select("span.scanstatus")
-                .and_then(|el| el.text())
-                .unwrap_or_default();
-            manga.status = match status_text.as_str() {
-                "Yes" => MangaStatus::Completed,
-                "No" => MangaStatus::Ongoing,
-                _ => MangaStatus::Unknown,
-            };
-            manga.tags = html.select("div.genres a.tag").map(|els| {
-                els.filter_map(|e| e.text()).collect::<Vec<String>>()
-            });
-            // Content rating classification
-            if let Some(tags) = &manga.tags {
-                let nsfw_terms = ["Doujinshi", "Adult", "Mature", "Smut"];
-                if tags.iter().any(|t| nsfw_terms.iter().any(|n| t == n)) {
-                    manga.content_rating = ContentRating::NSFW;
-                } else if tags.iter().any(|t| t == "Ecchi") {
-                    manga.content_rating = ContentRating::Suggestive;
-                } else {
-                    manga.content_rating = ContentRating::Safe;
-                }
-                // Viewer classification
-                if tags.iter().any(|t| t == "Manga") {
-                    manga.viewer = Viewer::RightToLeft;
-                } else if tags.iter().any(|t| matches!(t.as_str(), "Manhwa" | "Manhua" | "Webtoon")) {
-                    manga.viewer = Viewer::Webtoon;
-                } else {
-                    manga.viewer = Viewer::Unknown;
-                }
-            }
+            parse_details(&html, &mut manga);
         }
 
         if needs_chapters {
-            manga.chapters = html.select("table#index-table > tbody > tr").map(|rows| {
-                rows.filter_map(|row| {
-                    let link = row.select_first("td:nth-child(6) a")?;
-                    let href = link.attr("href")?;
-                    let key = normalize_chapter_href(&href);
-                    let title = row.select_first("td:nth-child(1) a").and_then(|a| a.text());
-                    let date_raw = row.select_first("td:nth-child(3)").and_then(|d| d.text()).unwrap_or_default();
-                    let date_uploaded = parse_chapter_date(&date_raw);
-                    let chapter_num = title
-                        .as_ref()
-                        .and_then(|t| t.split(' ').find_map(|s| s.parse::<f32>().ok()))
-                        .unwrap_or(-1.0);
-                    let url_full = format!("{BASE_URL}{}", key);
-                    Some(Chapter { key, title, chapter_number: Some(chapter_num), date_uploaded: Some(date_uploaded), url: Some(url_full), ..Default::default() })
-                })
-                .rev()
-                .collect::<Vec<Chapter>>()
-            });
+            // Rows come back in table order; folders are walked breadth-first up to MAX_SUBDIR_DEPTH.
+            // The loose files of the series folder are the newest, then subfolders from the last
+            // listed, so a chapter cap drops the oldest volumes.
+            let mut scan = IndexScan::new(&manga.key);
+            let (mut chapters, mut pending) = parse_index_pages(&html, &manga.key, &mut scan)?;
+            // Folders above series level (an index folder listed from a deep link) hold series,
+            // not chapters: their subfolders are never crawled
+            if is_intermediate_directory(&manga.key) { pending.clear(); }
+            let single_page = pending.is_empty() && scan.extra_pages == 0;
+            let mut depth = 1;
+            while !pending.is_empty() && depth <= MAX_SUBDIR_DEPTH {
+                let (mut walked, mut next) = (Vec::new(), Vec::new());
+                for dir in pending.into_iter().rev() {
+                    if scan.remaining == 0 { scan.truncated = true; break; }
+                    let dir_url = MadokamiUrl::new().path(&dir).build();
+                    let sub_html = match load_html(&dir_url, true, false)? {
+                        Fetched::Page(html) => html,
+                        // A folder that needs an elevated account is listed as one locked entry
+                        // instead of failing the series; one removed since the listing is skipped
+                        Fetched::Refused(MadokamiError::Status { status: 401 | 403, .. }) => {
+                            walked.push(alloc::vec![locked_folder(&dir)]);
+                            continue;
+                        }
+                        Fetched::Refused(_) => continue,
+                        Fetched::NotModified => fetch_html(&dir_url)?,
+                    };
+                    // Chapter hrefs of a moved subfolder resolve against its new path
+                    let dir = moved_key(&dir_url).unwrap_or(dir);
+                    let (mut sub_chapters, sub_dirs) = parse_index_pages(&sub_html, &dir, &mut scan)?;
+                    // Volume subfolders ("Vol. 03") supply the volume for files whose names lack one
+                    if let Some(volume) = dir.rsplit('/').next().map(percent_decode).as_deref().and_then(parse_volume_number) {
+                        for chapter in sub_chapters.iter_mut().filter(|c| c.volume_number.is_none()) {
+                            chapter.volume_number = Some(volume);
+                        }
+                    }
+                    walked.push(sub_chapters);
+                    next.push(sub_dirs);
+                }
+                chapters.extend(walked.into_iter().rev().flatten());
+                pending = next.into_iter().rev().flatten().collect();
+                depth += 1;
+            }
+            let mut chapters = dedup_formats(chapters);
+            filter_archive_kind(&mut chapters);
+            filter_release_kind(&mut chapters);
+            number_extras(&mut chapters);
+            sort_chapters(&mut chapters);
+            if let Some(lang) = raw_language(&manga.key) {
+                for chapter in chapters.iter_mut() { chapter.language = Some(lang.into()); }
+            }
+            if single_page && scan.continuation(&manga.key).is_none() {
+                cache::set_validator(&listing_key(&manga.key), &chapter_list_fingerprint(&manga.key));
+            } else {
+                defaults_set(&listing_key(&manga.key), DefaultValue::Null);
+            }
+            // The placeholder goes at the oldest end; "Oldest First" then reverses the whole list
+            chapters.extend(scan.continuation(&manga.key));
+            if defaults_get::<String>("chapterOrder").as_deref() == Some("asc") { chapters.reverse(); }
+            manga.chapters = Some(chapters);
+            manga.next_update_time = manga.chapters.as_deref().and_then(estimate_next_update);
         }
 
         Ok(manga)
     }
 
     fn get_page_list(&self, _manga: Manga, chapter: Chapter) -> Result<Vec<Page>> {
-        let url = format!("{BASE_URL}{}", chapter.key);
-        let html = auth_get(&url)?.html()?;
-        let (data_path, files_json) = if let Some(el) = html.select("div#reader").and_then(|els| els.first()) {
-            (el.attr("data-path").unwrap_or_default(), el.attr("data-files").unwrap_or_default())
-        } else { (String::new(), String::new()) };
-        if data_path.is_empty() || files_json.is_empty() { return Ok(Vec::new()); }
-        let files: Vec<String> = serde_json::from_str(&files_json).unwrap_or_default();
-        let pages = files.into_iter().map(|file| {
-            let page_url = format!(
-                "{BASE_URL}/reader/image?path={}&file={}",
-                encode_component(&data_path),
-                encode_component(&file)
-            );
-            Page { content: PageContent::url(page_url), ..Default::default() }
+        // Novel and document chapters are keyed by their file path, which reader_data_path passes
+        // through; keys stored with a /reader prefix resolve to the same file
+        if let Some(manga_key) = chapter.key.strip_suffix(MORE_CHAPTERS_MARKER) {
+            return Ok(alloc::vec![load_more_chapters(manga_key)]);
+        }
+        let data_path = reader_data_path(&chapter.key);
+        #[cfg(feature = "novels")]
+        if is_novel_file(&data_path) {
+            return novel_pages(&data_path);
+        }
+        // Documents aren't image archives: explain instead of opening an empty reader
+        if let Some(ext) = extension(&data_path).filter(|e| e == "pdf" || e == "epub") {
+            return Ok(alloc::vec![document_page(&chapter.key, &ext)]);
+        }
+        let url = MadokamiUrl::new().path(&chapter.key).build();
+        let sync_read = defaults_get::<bool>("syncReadMarkers").unwrap_or(false);
+        // Read sync needs the live reader page, so the cache is only consulted without it
+        let cached = if sync_read { None } else { cached_reader_files(&chapter.key) };
+        let (mut archives, cookie) = match cached {
+            Some(cached) => cached,
+            None => {
+                let (archives, cookie) = fetch_reader_files(&url, &chapter.key, sync_read)?;
+                store_reader_files(&chapter.key, &archives, cookie.as_deref());
+                (archives, cookie)
+            }
+        };
+        let skip_credits = defaults_get::<bool>("skipCreditPages").unwrap_or(false);
+        for (_, files) in archives.iter_mut() {
+            // Archives may list "1.png, 10.png, 2.png"
+            files.sort_by(|a, b| natural_cmp(a, b));
+            // Keep everything if the filter would leave nothing to read
+            if skip_credits && files.iter().any(|f| !is_credit_page(f)) {
+                files.retain(|f| !is_credit_page(f));
+            }
+        }
+        let params = image_params();
+        // Slider previews use the resize parameter at a small width. Opt-in: a server ignoring the
+        // parameter would send every page a second time at full size
+        let thumb_params = defaults_get::<bool>("pageThumbnails").unwrap_or(false)
+            .then(|| alloc::vec![("width", PAGE_THUMBNAIL_WIDTH.to_string())]);
+        let mut context = PageContext::new();
+        context.insert("referer".into(), url.clone());
+        if let Some(cookie) = cookie { context.insert("cookie".into(), cookie); }
+        if defaults_get::<bool>("validatePages").unwrap_or(false) {
+            if let Some((data_path, file)) = archives.iter().find_map(|(p, files)| files.first().map(|f| (p, f))) {
+                validate_page_url(&reader_image_url(data_path, file, &params), &url)?;
+            }
+        }
+        let mut pages = archives.into_iter().flat_map(|(data_path, files)| {
+            files.into_iter().map(move |file| (data_path.clone(), file))
+        }).map(|(data_path, file)| {
+            let page_url = reader_image_url(&data_path, &file, &params);
+            let thumbnail = thumb_params.as_deref().map(|params| reader_image_url(&data_path, &file, params));
+            // The archive entry name identifies the page in bug reports and saved images
+            Page {
+                content: PageContent::url_context(page_url, context.clone()),
+                thumbnail,
+                has_description: true,
+                description: Some(file),
+                ..Default::default()
+            }
         }).collect::<Vec<Page>>();
+        if defaults_get::<bool>("downloadLinkPage").unwrap_or(false) {
+            let text = format!("Original file: {}", download_url(&chapter.key));
+            pages.push(Page { content: PageContent::Text(text), ..Default::default() });
+        }
         Ok(pages)
     }
 }
@@ -302,51 +1610,509 @@ impl Source for Madokami {
 impl ListingProvider for Madokami {
     fn get_manga_list(&self, listing: Listing, page: i32) -> Result<MangaPageResult> {
         if listing.id == "recent" {
-            let url = format!("{BASE_URL}/recent?page={}", page);
-            let html = auth_get(&url)?.html()?;
-            let entries = html
-                .select("table.mobile-files-table tbody tr")
-                .map(|rows| {
-                    rows.filter_map(|row| {
-                        let link = row.select_first("td:nth-child(1) a:nth-child(1)")?;
-                        let key = link.attr("href")?;
-                        let (title, description) = derive_from_path(&key);
-                        if title.is_empty() { return None; }
-                        Some(Manga { key, title, description: description.filter(|d| !d.is_empty()), ..Default::default() })
-                    }).collect::<Vec<Manga>>()
-                }).unwrap_or_default();
-            let has_next_page = html
-                .select("a.pagination-next")
-                .map(|els| els.filter_map(|_| Some(())).next().is_some())
-                .unwrap_or(false);
-            Ok(MangaPageResult { entries, has_next_page })
+            recent_entries(page)
+        } else if let Some(dir) = listing.id.strip_prefix(DIRECTORY_LISTING_PREFIX) {
+            // Intermediate folders (publisher/initial indexes) list their subfolders as entries
+            let html = cached_html(&MadokamiUrl::new().path(dir).build(), false)?;
+            Ok(MangaPageResult { entries: visible_entries(directory_entries(&html, dir)), has_next_page: false })
+        } else if let Some(genre) = listing.id.strip_prefix(GENRE_LISTING_PREFIX) {
+            let html = cached_html(&MadokamiUrl::new().path("/tags").segment(genre).build(), false)?;
+            Ok(MangaPageResult { entries: visible_entries(search_entries(&html)), has_next_page: false })
+        } else if let Some(query) = listing.id.strip_prefix(SEARCH_LISTING_PREFIX) {
+            // Opened from a shared search link
+            self.get_search_manga_list(Some(query.into()), page, Vec::new())
         } else {
-            bail!("Unimplemented listing")
+            MadokamiError::UnknownListing { id: listing.id }.fail()
+        }
+    }
+}
+
+// Series details from a folder page. Everything lives in the info panel, so the queries run
+// against that subtree (one combined query for authors/artists) instead of the whole page.
+fn parse_details(html: &Document, manga: &mut Manga) {
+    let Some(root) = selectors::DETAILS.first(html).map(|info| info.parent().unwrap_or(info)) else { return };
+
+    manga.cover = selectors::COVER.first(&root).and_then(|el| el.attr("src"));
+    // Re-derive title/description from key if not already set
+    if manga.title.is_empty() {
+        let (title, desc) = derive_from_path(&manga.key);
+        if !title.is_empty() { manga.title = title; }
+        if manga.description.is_none() { manga.description = desc; }
+    }
+    if let Some(title_override) = selectors::TITLE.first(&root).and_then(|el| el.text()) {
+        if !title_override.is_empty() { manga.title = localized_title(&title_override); }
+    }
+    let (mut authors, mut artists) = (Vec::new(), Vec::new());
+    for el in root.select("a[itemprop='author'], a[itemprop='artist']").into_iter().flatten() {
+        let Some(name) = el.text() else { continue };
+        if el.attr("itemprop").as_deref() == Some("artist") { artists.push(name) } else { authors.push(name) }
+    }
+    manga.authors = Some(authors);
+    manga.artists = Some(artists);
+    manga.description = selectors::SYNOPSIS.first(&root).and_then(|el| el.text());
+    let status_text = root.select_first("span.scanstatus").and_then(|el| el.text()).unwrap_or_default();
+    manga.status = match status_text.as_str() {
+        "Yes" => MangaStatus::Completed,
+        "No" => MangaStatus::Ongoing,
+        _ => MangaStatus::Unknown,
+    };
+    let tags = selectors::GENRES.all(&root).into_iter().filter_map(|e| e.text()).collect::<Vec<String>>();
+    manga.tags = (!tags.is_empty()).then_some(tags);
+    // Content rating classification
+    if let Some(tags) = &manga.tags {
+        let nsfw_terms = ["Doujinshi", "Adult", "Mature", "Smut"];
+        if tags.iter().any(|t| nsfw_terms.iter().any(|n| t == n)) {
+            manga.content_rating = ContentRating::NSFW;
+        } else if tags.iter().any(|t| t == "Ecchi") {
+            manga.content_rating = ContentRating::Suggestive;
+        } else {
+            manga.content_rating = ContentRating::Safe;
+        }
+        // Viewer classification
+        if tags.iter().any(|t| t == "Manga") {
+            manga.viewer = Viewer::RightToLeft;
+        } else if tags.iter().any(|t| matches!(t.as_str(), "Manhwa" | "Manhua" | "Webtoon")) {
+            manga.viewer = Viewer::Webtoon;
+        } else {
+            manga.viewer = Viewer::Unknown;
+        }
+    }
+}
+
+#[cfg(feature = "home")]
+fn cover_from_details(html: &Document) -> Option<String> {
+    selectors::COVER.first(html).and_then(|el| el.attr("src"))
+}
+
+// Covers of several series, fetched concurrently from their folder pages
+#[cfg(feature = "home")]
+fn series_covers(keys: &[&str]) -> Vec<Option<String>> {
+    let base = base_url();
+    let urls = keys.iter().map(|key| MadokamiUrl::with_base(&base).path(key).build()).collect::<Vec<String>>();
+    cached_html_all(&urls).iter().map(|doc| doc.as_ref().and_then(cover_from_details)).collect()
+}
+
+// One page of the /recent feed. Home and the Recent listing both open page 1 back to back, so
+// the parsed rows are kept briefly as "<has next>\n<key>\n<key>..." to skip a second parse.
+fn recent_entries(page: i32) -> Result<MangaPageResult> {
+    let url = MadokamiUrl::new().path("/recent").query("page", &page.to_string()).build();
+    let feed_key = format!("recent.{}", url);
+    if let Some(feed) = cache::get(&feed_key, RECENT_FEED_TTL) {
+        let mut lines = feed.split('\n');
+        let has_next_page = lines.next() == Some("1");
+        let base = base_url();
+        let entries = lines.filter_map(|key| entry_for_key(key.into(), &base)).collect::<Vec<Manga>>();
+        return Ok(MangaPageResult { entries: visible_entries(entries), has_next_page });
+    }
+
+    let html = cached_html(&url, false)?;
+    let entries = row_entries(&html, &selectors::RECENT_ROWS);
+    let has_next_page = selectors::NEXT_PAGE.first(&html).is_some();
+    let mut feed = String::from(if has_next_page { "1" } else { "0" });
+    for entry in &entries {
+        feed.push('\n');
+        feed.push_str(&entry.key);
+    }
+    cache::set(&feed_key, &feed);
+    Ok(MangaPageResult { entries: visible_entries(entries), has_next_page })
+}
+
+// =================================================================================
+// IMAGE REQUESTS
+// =================================================================================
+impl ImageRequestProvider for Madokami {
+    fn get_image_request(&self, url: String, context: Option<PageContext>) -> Result<Request> {
+        if url.ends_with(THUMBNAIL_MARKER) { return chapter_thumbnail_request(&url); }
+        let mut req = auth_get(&url)?;
+        // The image endpoint may reject hotlinked requests without the reader page as Referer
+        let referer = context.as_ref().and_then(|c| c.get("referer").cloned()).unwrap_or_else(|| MadokamiUrl::new().path("/").build());
+        req.set_header("Referer", &referer);
+        if let Some(cookie) = context.as_ref().and_then(|c| c.get("cookie")) {
+            req.set_header("Cookie", cookie);
         }
+        // Lets the server transcode if it can; image/* keeps the original format acceptable
+        if defaults_get::<bool>("modernImageFormats").unwrap_or(false) {
+            req.set_header("Accept", "image/avif,image/webp,image/*;q=0.8");
+        }
+        Ok(req)
     }
 }
 
 // =================================================================================
 // HOME & DEEPLINK
 // =================================================================================
+#[cfg(feature = "home")]
 impl Home for Madokami {
-    fn get_home(&self) -> Result<HomeLayout> { Ok(HomeLayout::default()) }
+    fn get_home(&self) -> Result<HomeLayout> {
+        let enabled = |key: &str, default: bool| defaults_get::<bool>(key).unwrap_or(default);
+        let recent = Listing { id: "recent".into(), name: "Recent".into(), ..Default::default() };
+        let mut components = Vec::new();
+
+        // Covers aren't in the feed, so the banner loads each series page: off by default
+        if enabled("homeFeatured", false) || enabled("homeRecent", true) {
+            let entries = recent_entries(1).map(|r| r.entries).unwrap_or_default();
+            if enabled("homeFeatured", false) {
+                let picks = &entries[..entries.len().min(HOME_FEATURED_COUNT)];
+                let keys = picks.iter().map(|m| m.key.as_str()).collect::<Vec<&str>>();
+                let featured = picks.iter().zip(series_covers(&keys)).filter_map(|(m, cover)| {
+                    Some(Manga { cover: Some(cover?), ..m.clone() })
+                }).collect::<Vec<Manga>>();
+                if !featured.is_empty() {
+                    components.push(HomeComponent {
+                        title: None,
+                        subtitle: None,
+                        value: HomeComponentValue::BigScroller { entries: featured, auto_scroll_interval: Some(8.0) },
+                    });
+                }
+            }
+            if enabled("homeRecent", true) && !entries.is_empty() {
+                components.push(HomeComponent {
+                    title: Some("Recently Updated".into()),
+                    subtitle: None,
+                    value: HomeComponentValue::MangaList {
+                        ranking: false,
+                        page_size: None,
+                        entries: entries.into_iter().map(|m| m.into()).collect(),
+                        listing: Some(recent),
+                    },
+                });
+            }
+        }
+
+        if enabled("homeSections", true) {
+            let hide_raws = defaults_get::<bool>("hideRaws").unwrap_or(false);
+            let show_adult = defaults_get::<bool>("adultSections").unwrap_or(false);
+            let links = HOME_SECTIONS.iter()
+                .filter(|(key, _)| !is_hidden_entry(key, hide_raws, show_adult))
+                .map(|(key, name)| Link {
+                    title: (*name).into(),
+                    value: Some(LinkValue::Listing(Listing {
+                        id: format!("{DIRECTORY_LISTING_PREFIX}{}", key),
+                        name: (*name).into(),
+                        ..Default::default()
+                    })),
+                    ..Default::default()
+                })
+                .collect::<Vec<Link>>();
+            components.push(HomeComponent {
+                title: Some("Browse".into()),
+                subtitle: None,
+                value: HomeComponentValue::Links(links),
+            });
+        }
+
+        Ok(HomeLayout { components })
+    }
 }
 
 impl DeepLinkHandler for Madokami {
     fn handle_deep_link(&self, url: String) -> Result<Option<DeepLinkResult>> {
-        if !url.starts_with(BASE_URL) { return Ok(None); }
-        let key = &url[BASE_URL.len()..];
-        if key.starts_with("reader/") || key.contains("/reader/") {
-            // Could attempt to split manga vs chapter; minimal: treat as chapter
-            return Ok(Some(DeepLinkResult::Chapter { manga_key: String::new(), key: key.into() }))
+        let Some(key) = deep_link_key(&url) else { return Ok(None) };
+        let key = key.as_str();
+        if key == "/recent" || key.starts_with("/recent?") {
+            let listing = Listing { id: "recent".into(), name: "Recent".into(), ..Default::default() };
+            return Ok(Some(DeepLinkResult::Listing(listing)));
+        }
+        if key == "/search" || key.starts_with("/search?") {
+            let query = key.split_once('?')
+                .and_then(|(_, q)| q.split('&').find_map(|kv| kv.strip_prefix("q=")))
+                .map(percent_decode)
+                .unwrap_or_default();
+            if query.trim().is_empty() { return Ok(None); }
+            let listing = Listing {
+                id: format!("{SEARCH_LISTING_PREFIX}{}", query),
+                name: format!("Search: {}", query),
+                ..Default::default()
+            };
+            return Ok(Some(DeepLinkResult::Listing(listing)));
+        }
+        if key.starts_with("reader/") || key.contains("/reader/") || key.starts_with("/reader?") {
+            let manga_key = series_key_for_reader(key);
+            return Ok(Some(DeepLinkResult::Chapter { manga_key, key: key.into() }))
+        }
+        // Genre landing pages: /tags/<Genre> (also /genre/<Genre>)
+        if let Some(genre) = key.strip_prefix("/tags/").or_else(|| key.strip_prefix("/genre/")) {
+            let genre = percent_decode(genre.split(['?', '/']).next().unwrap_or_default());
+            if genre.is_empty() { return Ok(None); }
+            let listing = Listing {
+                id: format!("{GENRE_LISTING_PREFIX}{}", genre),
+                name: genre,
+                ..Default::default()
+            };
+            return Ok(Some(DeepLinkResult::Listing(listing)));
+        }
+        // Everything else is a folder; queries like "?sort=asc" are view options, not part of the key
+        let key = key.split('?').next().unwrap_or(key);
+        if key == "/" { return Ok(None); }
+        if is_intermediate_directory(key) {
+            let (title, _) = derive_from_path(key);
+            let listing = Listing {
+                id: format!("{DIRECTORY_LISTING_PREFIX}{}", key.trim_end_matches('/')),
+                name: if title.is_empty() { "Madokami".into() } else { title },
+                ..Default::default()
+            };
+            return Ok(Some(DeepLinkResult::Listing(listing)));
         }
         Ok(Some(DeepLinkResult::Manga { key: key.into() }))
     }
 }
 
+// =================================================================================
+// SETTINGS ACTIONS
+// =================================================================================
+impl NotificationHandler for Madokami {
+    fn handle_notification(&self, notification: String) {
+        if notification == "clearCache" { cache::clear(); }
+    }
+}
+
 // =================================================================================
 // REGISTER SOURCE
 // =================================================================================
-register_source!(Madokami, ListingProvider, Home, DeepLinkHandler);
+// register_source! takes a fixed trait list, so each feature combination gets its own call
+#[cfg(feature = "home")]
+register_source!(Madokami, ListingProvider, Home, DeepLinkHandler, ImageRequestProvider, NotificationHandler);
+#[cfg(not(feature = "home"))]
+register_source!(Madokami, ListingProvider, DeepLinkHandler, ImageRequestProvider, NotificationHandler);
 
+
+// =================================================================================
+// TESTS
+// =================================================================================
+#[cfg(test)]
+mod test {
+    use super::*;
+    use aidoku_test::aidoku_test;
+
+    // Hand-written pages in the site's markup, one branch of each parser per row, parsed as the
+    // source would receive them
+    fn fixture(body: &str, path: &str) -> Document {
+        Html::parse_with_url(body, &MadokamiUrl::new().path(path).build()).ok().expect("fixture parses")
+    }
+
+    #[aidoku_test]
+    fn search_page_fixture() {
+        let html = fixture(include_str!("../tests/fixtures/search.html"), "/search");
+        let entries = search_entries(&html);
+        let keys = entries.iter().map(|m| m.key.as_str()).collect::<Vec<&str>>();
+        assert_eq!(keys, [
+            "/Manga/B/BE/BERS/Berserk",
+            "/Manga/B/BE/BERS/Berserk%20-%20Colored%20%28Digital%29",
+            "/Manga/_Doujinshi/Berserk/%21Anthology",
+            "/Raws/Japanese/%E3%83%99%E3%83%AB%E3%82%BB%E3%83%AB%E3%82%AF",
+        ]);
+        let titles = entries.iter().map(|m| m.title.as_str()).collect::<Vec<&str>>();
+        // "!" folders are named after their parent
+        assert_eq!(titles, ["Berserk", "Berserk - Colored (Digital)", "Berserk", "ベルセルク"]);
+    }
+
+    #[aidoku_test]
+    fn recent_page_fixture() {
+        let html = fixture(include_str!("../tests/fixtures/recent.html"), "/recent");
+        let entries = row_entries(&html, &selectors::RECENT_ROWS);
+        let keys = entries.iter().map(|m| m.key.as_str()).collect::<Vec<&str>>();
+        assert_eq!(keys, ["/Manga/O/ON/ONEP/One%20Piece", "/Manga/K/KI/KING/Kingdom"]);
+        assert_eq!(entries[0].title, "One Piece");
+    }
+
+    #[aidoku_test]
+    fn series_page_fixture() {
+        let dir = "/Manga/B/BE/BERS/Berserk";
+        let html = fixture(include_str!("../tests/fixtures/series.html"), dir);
+        let mut scan = IndexScan::new(dir);
+        let (chapters, subdirs) = parse_index_table(&html, dir, &mut scan);
+        assert_eq!(subdirs, ["/Manga/B/BE/BERS/Berserk/Extras"]);
+        // Browsing the folder lists only the subfolder, not the .nfo/.pdf files
+        let entries = directory_entries(&html, dir);
+        assert_eq!(entries.iter().map(|m| m.key.as_str()).collect::<Vec<&str>>(), ["/Manga/B/BE/BERS/Berserk/Extras"]);
+        // The .nfo row is dropped; the locked archive and the PDF have no reader link
+        let keys = chapters.iter().map(|c| c.key.as_str()).collect::<Vec<&str>>();
+        assert_eq!(keys, [
+            "/reader/Manga/B/BE/BERS/Berserk/Berserk%20v01%20%5BDark%20Horse%5D.zip",
+            "/Manga/B/BE/BERS/Berserk/Berserk%20Official%20Guidebook.pdf",
+            "/reader/Manga/B/BE/BERS/Berserk/Berserk%20-%20c364%20%5Bcatbox%5D.zip",
+            "/reader/Manga/B/BE/BERS/Berserk/Berserk%20-%20c365.zip",
+        ]);
+        assert_eq!(chapters[0].volume_number, Some(1.0));
+        let c364 = &chapters[2];
+        assert_eq!(c364.title.as_deref(), Some("Berserk - c364 [catbox]"));
+        assert_eq!(c364.chapter_number, Some(364.0));
+        assert_eq!(c364.scanlators.as_deref(), Some(&["casca".to_string()][..]));
+        assert_eq!(c364.date_uploaded, Some(1631243700));
+        assert!(!c364.locked);
+        assert!(chapters[3].locked);
+        assert_eq!(chapters[3].title.as_deref(), Some("Berserk - c365 (locked: account lacks access)"));
+    }
+
+    #[aidoku_test]
+    fn reader_page_fixture() {
+        let html = fixture(include_str!("../tests/fixtures/reader.html"), "/reader/Manga/B/BE/BERS/Berserk/Berserk%20v01%20%5BDark%20Horse%5D.zip");
+        let archives = reader_files(&html);
+        assert_eq!(archives.len(), 1);
+        let (data_path, files) = &archives[0];
+        assert_eq!(data_path, "/Manga/B/BE/BERS/Berserk/Berserk v01 [Dark Horse].zip");
+        assert_eq!(files, &["Berserk v01/001.jpg", "Berserk v01/002.jpg", "Berserk v01/003 (credits).png"]);
+        assert!(reader_continuation(&html).is_none());
+    }
+
+    #[aidoku_test]
+    fn unescape_html_entities() {
+        assert_eq!(unescape_html("[&quot;001.jpg&quot;,&quot;002.jpg&quot;]"), "[\"001.jpg\",\"002.jpg\"]");
+        assert_eq!(unescape_html("Tom &amp; Jerry &#39;01&#x27; &lt;v2&gt;"), "Tom & Jerry '01' <v2>");
+        // Bare ampersands and unknown entities are kept as written
+        assert_eq!(unescape_html("AT&T"), "AT&T");
+        assert_eq!(unescape_html("a & b;"), "a & b;");
+        assert_eq!(unescape_html("&bogus; &"), "&bogus; &");
+    }
+
+    #[aidoku_test]
+    fn decode_reader_file_lists() {
+        // data-files as the reader page writes it, and double-escaped by older pages
+        assert_eq!(decode_file_list(r#"["001.jpg", "002.jpg"]"#), ["001.jpg", "002.jpg"]);
+        assert_eq!(decode_file_list("[&quot;vol01/001.png&quot;,&quot;vol01/002.png&quot;]"), ["vol01/001.png", "vol01/002.png"]);
+        // JSON escapes, including \/ and surrogate pairs
+        assert_eq!(decode_file_list(r#"["caf\u00e9\/01.jpg", "\ud83d\ude00.png"]"#), ["café/01.jpg", "😀.png"]);
+        // Base64-wrapped payloads
+        assert_eq!(decode_file_list("WyIwMDEuanBnIiwgIjAwMi5qcGciXQ=="), ["001.jpg", "002.jpg"]);
+        assert!(decode_file_list("[]").is_empty());
+        assert!(decode_file_list("not a list").is_empty());
+    }
+
+    #[aidoku_test]
+    fn percent_decode_escapes() {
+        assert_eq!(percent_decode("Title%20v01+(2019).zip"), "Title v01 (2019).zip");
+        assert_eq!(percent_decode("caf%C3%A9"), "café");
+        assert_eq!(percent_decode("caf%c3%a9"), "café");
+        assert_eq!(percent_decode("no escapes"), "no escapes");
+    }
+
+    #[aidoku_test]
+    fn percent_decode_keeps_incomplete_escapes() {
+        assert_eq!(percent_decode("100%"), "100%");
+        assert_eq!(percent_decode("50%2"), "50%2");
+        assert_eq!(percent_decode("%2"), "%2");
+        assert_eq!(percent_decode("%zz"), "%zz");
+        assert_eq!(percent_decode("%%41"), "%A");
+        // A trailing complete escape still decodes
+        assert_eq!(percent_decode("a%2F"), "a/");
+    }
+
+    #[aidoku_test]
+    fn percent_decode_invalid_utf8() {
+        // Stray or cut-off multibyte sequences become replacement characters
+        assert_eq!(percent_decode("%FF"), "\u{FFFD}");
+        assert_eq!(percent_decode("caf%C3"), "caf\u{FFFD}");
+        assert_eq!(percent_decode("%C3%A9%E2%82"), "é\u{FFFD}");
+    }
+
+    // Deterministic pseudo-random strings (xorshift32) of up to 15 characters from `alphabet`
+    fn random_strings(alphabet: &[char], count: usize) -> Vec<String> {
+        let mut state: u32 = 0x9E37_79B9;
+        let mut next = move || {
+            state ^= state << 13;
+            state ^= state >> 17;
+            state ^= state << 5;
+            state as usize
+        };
+        (0..count).map(|_| (0..next() % 16).map(|_| alphabet[next() % alphabet.len()]).collect()).collect()
+    }
+
+    #[aidoku_test]
+    fn percent_decode_random_input() {
+        // Arbitrary strings heavy in escape characters must decode without panicking
+        for input in random_strings(&['%', '%', '+', '0', '9', 'a', 'F', 'g', '/', ' ', 'é', '\u{1F600}'], 2000) {
+            let decoded = percent_decode(&input);
+            assert!(decoded.len() <= input.len() * 3);
+        }
+    }
+
+    #[aidoku_test]
+    fn malformed_paths() {
+        assert_eq!(derive_from_path(""), (String::new(), None));
+        assert_eq!(derive_from_path("////"), (String::new(), None));
+        // Only "!" folders: no title to fall back on
+        assert_eq!(derive_from_path("/!a/!b").0, "");
+        assert_eq!(canonical_series_key(""), "");
+        assert_eq!(canonical_series_key("/Raws/"), "/Raws/");
+        assert_eq!(canonical_series_key("/Raws/Japanese/Title/!a/!b"), "/Raws/Japanese/Title");
+        assert_eq!(canonical_series_key("/Manga/A/AB/ABCD/Title/Sub/!x"), "/Manga/A/AB/ABCD/Title");
+        assert_eq!(series_key_for_reader(""), "");
+        assert_eq!(series_key_for_reader("/reader?path="), "");
+        assert_eq!(reader_data_path("/reader"), "");
+        assert_eq!(reader_data_path("?#"), "");
+        assert_eq!(strip_extension("."), ".");
+        assert_eq!(strip_extension(" .zip "), "");
+        assert_eq!(unescape_html("&"), "&");
+        assert_eq!(unescape_html("&;"), "&;");
+        assert_eq!(unescape_html("&#xZZ; &#99999999999; &#xD800;"), "&#xZZ; &#99999999999; &#xD800;");
+    }
+
+    #[aidoku_test]
+    fn random_paths_and_names() {
+        // Path and filename parsers return something (or nothing) for any input; none may panic
+        let alphabet = ['/', '!', '.', '-', ' ', '&', ';', '#', 'x', '<', '>', 'v', 'c', '1', '%', '?', 'é', '\u{1F600}'];
+        for input in random_strings(&alphabet, 2000) {
+            let _ = derive_from_path(&input);
+            let _ = canonical_series_key(&input);
+            let _ = series_key_for_reader(&input);
+            let _ = reader_data_path(&input);
+            let _ = strip_extension(&input);
+            let _ = unescape_html(&input);
+            let _ = parse_chapter_number(&input);
+            let _ = parse_volume_number(&input);
+            let _ = date::parse_chapter_date(&input, 0);
+            #[cfg(feature = "novels")]
+            let _ = html_to_text(&input);
+        }
+    }
+
+    #[aidoku_test]
+    fn malformed_string_arrays() {
+        for json in [
+            "", "[", "]", "[\"a\"", "[\"a\",]", "[,\"a\"]", "[\"a\" \"b\"]", "[\"a\"] x", "[1]", "[[\"a\"]]",
+            "[\"\\\"]", "[\"\\q\"]", "[\"\\u12\"]", "[\"\\uéééé\"]",
+            // Lone or mismatched surrogates
+            "[\"\\ud83d\"]", "[\"\\ud83d\\u0041\"]", "[\"\\udc00\"]",
+        ] {
+            assert_eq!(parse_string_array(json), None, "{json}");
+        }
+        assert_eq!(parse_string_array(" [ ] "), Some(Vec::new()));
+        assert_eq!(parse_string_array("[\"é\", \"\\u00e9\"]"), Some(alloc::vec!["é".into(), "é".into()]));
+        for input in random_strings(&['[', ']', '"', '\\', 'u', 'd', '8', '0', ',', ' ', 'é'], 2000) {
+            let _ = parse_string_array(&input);
+        }
+    }
+
+    #[aidoku_test]
+    fn redirects_only_move_entries_to_series_folders() {
+        assert!(is_series_key("/Manga/B/BE/BERS/Berserk"));
+        assert!(is_series_key("/Raws/Japanese/Berserk"));
+        assert!(is_series_key("/Novels/Overlord"));
+        for key in ["/Manga", "/Manga/B/BE/BERS", "/Raws/Japanese", "/Novels", "/Manga/B/BE/BERS/Berserk/Berserk v01.zip", "/Doujinshi/Berserk", "/search"] {
+            assert!(!is_series_key(key), "{key}");
+        }
+    }
+
+    #[cfg(feature = "novels")]
+    #[aidoku_test]
+    fn novel_text_is_split_into_page_sized_pieces() {
+        let line = "word ".repeat(NOVEL_PAGE_CHARS / 5 * 3);
+        let text = format!("Short paragraph.\n\n{line}\nNext line.");
+        let pieces = novel_pieces(&text);
+        assert!(pieces.iter().all(|(piece, _)| piece.len() <= NOVEL_PAGE_CHARS && !piece.is_empty()));
+        assert_eq!(pieces.first(), Some(&("Short paragraph.", true)));
+        assert_eq!(pieces.last(), Some(&("Next line.", false)));
+        assert_eq!(pieces.iter().filter(|(_, new_paragraph)| *new_paragraph).count(), 2);
+        // Multibyte text without spaces is cut on character boundaries
+        let run_on = "é".repeat(NOVEL_PAGE_CHARS);
+        assert!(novel_pieces(&run_on).iter().all(|(piece, _)| piece.len() <= NOVEL_PAGE_CHARS));
+    }
+
+    #[aidoku_test]
+    fn rendered_titles_keep_chapter_ranges() {
+        let groups = ["catbox".to_string()];
+        assert_eq!(render_chapter_title(Some(2.0), Some(13.5), None, Some(&groups[..])).as_deref(), Some("Vol. 2 Ch. 13.5 — catbox"));
+        assert_eq!(render_chapter_title(None, Some(1.0), Some(10.0), None).as_deref(), Some("Ch. 1-10"));
+        assert_eq!(render_chapter_title(None, None, None, Some(&groups[..])), None);
+    }
+}
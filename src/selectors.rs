@@ -0,0 +1,103 @@
+// Ordered selector fallbacks for the page elements the source depends on. The first selector is
+// the current markup; later ones cover older or redesigned layouts. A selector pasted into the
+// chain's advanced setting is tried before all of them, so site changes can be hotfixed without
+// a new build. When a fallback is what matched, it is recorded under "selectorFallback.<name>"
+// so bug reports can say which markup the site is serving.
+use aidoku::{
+    alloc::{String, Vec},
+    imports::{
+        defaults::{defaults_get, defaults_set, DefaultValue},
+        html::{Document, Element, ElementList},
+    },
+};
+
+// Anything selectors can run against
+pub trait Scope {
+    fn query(&self, selector: &str) -> Option<ElementList>;
+    fn query_first(&self, selector: &str) -> Option<Element>;
+}
+
+impl Scope for Document {
+    fn query(&self, selector: &str) -> Option<ElementList> { self.select(selector) }
+    fn query_first(&self, selector: &str) -> Option<Element> { self.select_first(selector) }
+}
+
+impl Scope for Element {
+    fn query(&self, selector: &str) -> Option<ElementList> { self.select(selector) }
+    fn query_first(&self, selector: &str) -> Option<Element> { self.select_first(selector) }
+}
+
+pub struct Chain {
+    pub name: &'static str,
+    // Settings key holding a user override, for chains exposed in the advanced settings
+    pub setting: Option<&'static str>,
+    pub selectors: &'static [&'static str],
+}
+
+impl Chain {
+    // Override from the advanced settings, if the user entered one
+    pub fn user_selector(&self) -> Option<String> {
+        defaults_get::<String>(self.setting?)
+            .map(|s| s.trim().into())
+            .filter(|s: &String| !s.is_empty())
+    }
+
+    // First element of the first selector that matches
+    pub fn first(&self, scope: &impl Scope) -> Option<Element> {
+        if let Some(el) = self.user_selector().and_then(|user| scope.query_first(&user)) { return Some(el); }
+        self.selectors.iter().enumerate().find_map(|(i, selector)| {
+            let el = scope.query_first(selector)?;
+            self.record(i);
+            Some(el)
+        })
+    }
+
+    // All elements of the first selector that matches anything
+    pub fn all(&self, scope: &impl Scope) -> Vec<Element> {
+        if let Some(user) = self.user_selector() {
+            let found = scope.query(&user).map(|els| els.collect::<Vec<Element>>()).unwrap_or_default();
+            if !found.is_empty() { return found; }
+        }
+        for (i, selector) in self.selectors.iter().enumerate() {
+            let found = scope.query(selector).map(|els| els.collect::<Vec<Element>>()).unwrap_or_default();
+            if !found.is_empty() {
+                self.record(i);
+                return found;
+            }
+        }
+        Vec::new()
+    }
+
+    // Remember which selector matched when it wasn't the primary one
+    pub fn record(&self, index: usize) {
+        if index == 0 { return; }
+        let Some(&selector) = self.selectors.get(index) else { return };
+        let key = ["selectorFallback.", self.name].concat();
+        if defaults_get::<String>(&key).as_deref() != Some(selector) {
+            defaults_set(&key, DefaultValue::String(selector.into()));
+        }
+    }
+}
+
+pub const SEARCH_ROWS: Chain = Chain { name: "searchRows", setting: None, selectors: &["div.container table tbody tr", "table tbody tr"] };
+pub const RECENT_ROWS: Chain = Chain {
+    name: "recentRows",
+    setting: None,
+    selectors: &["table.mobile-files-table tbody tr", "table#index-table tbody tr", "table tbody tr"],
+};
+pub const NEXT_PAGE: Chain = Chain { name: "nextPage", setting: None, selectors: &["a.pagination-next", "ul.pagination li.next a", "a[rel='next']"] };
+pub const INDEX_TABLE: Chain = Chain { name: "indexTable", setting: None, selectors: &["table#index-table", "table.mobile-files-table", "table.table"] };
+pub const READER: Chain = Chain { name: "reader", setting: Some("readerSelector"), selectors: &["div#reader, div.reader[data-path]", "[data-path][data-files]"] };
+pub const DETAILS: Chain = Chain { name: "details", setting: None, selectors: &["div.manga-info", "[itemtype$='Book']", "body"] };
+pub const COVER: Chain = Chain {
+    name: "cover",
+    setting: Some("coverSelector"),
+    selectors: &["div.manga-info img[itemprop='image']", "img[itemprop='image']", "div.manga-info img"],
+};
+pub const TITLE: Chain = Chain { name: "title", setting: None, selectors: &["div.manga-info-title h1", "h1[itemprop='name']"] };
+pub const SYNOPSIS: Chain = Chain { name: "synopsis", setting: None, selectors: &["div.manga-info-synopsis", "[itemprop='description']"] };
+pub const GENRES: Chain = Chain { name: "genres", setting: None, selectors: &["div.genres a.tag", "a.tag[href^='/tags/']"] };
+
+// Reader link of an index table row; only the user override is consulted per row, the built-in
+// fallbacks are the row's 6th cell and any /reader link
+pub const CHAPTER_LINK: Chain = Chain { name: "chapterLink", setting: Some("chapterLinkSelector"), selectors: &[] };
@@ -0,0 +1,135 @@
+// URLs on the configured Madokami host. Every request URL goes through MadokamiUrl so the base
+// URL override and the encoding rules below apply everywhere:
+//   - site paths/hrefs (series keys, reader keys) are appended as the site wrote them
+//   - decoded file paths are encoded like the site's hrefs (sub-delimiters stay literal)
+//   - query values are encoded strictly, except '/' separators in path-valued parameters
+use aidoku::alloc::{String, Vec};
+
+const HEX_DIGITS: &[u8; 16] = b"0123456789ABCDEF";
+
+// Percent-encode every byte `keep` rejects, in a single preallocated pass
+fn percent_encode(s: &str, keep: impl Fn(u8) -> bool) -> String {
+    if s.bytes().all(&keep) { return s.into(); }
+    let mut out = String::with_capacity(s.len() * 3 / 2);
+    for b in s.bytes() {
+        if keep(b) { out.push(b as char); } else {
+            out.push('%');
+            out.push(HEX_DIGITS[(b >> 4) as usize] as char);
+            out.push(HEX_DIGITS[(b & 0x0F) as usize] as char);
+        }
+    }
+    out
+}
+
+// Encode strictly for query component (space -> %20, etc.)
+fn encode_component(s: &str) -> String {
+    percent_encode(s, |b| b.is_ascii_alphanumeric() || matches!(b, b'-' | b'_' | b'.' | b'~'))
+}
+
+// Encode a query value that holds a path, keeping the '/' separators. '/' is legal in a query value
+// and the image endpoint expects archive-internal paths ("vol01/001.png") with literal separators.
+fn encode_query_path(path: &str) -> String {
+    path.split('/').map(encode_component).collect::<Vec<String>>().join("/")
+}
+
+// Encode a URL path the way the site writes its hrefs: sub-delimiters like '!' '(' ',' stay literal
+pub fn encode_path(path: &str) -> String {
+    percent_encode(path, |b| b.is_ascii_alphanumeric() || b"-_.~/!$&'()*+,;=:@".contains(&b))
+}
+
+pub struct MadokamiUrl {
+    url: String,
+    has_query: bool,
+}
+
+impl MadokamiUrl {
+    // Rooted at the configured base URL
+    pub fn new() -> Self {
+        Self::with_base(&crate::base_url())
+    }
+
+    // Rooted at an already resolved base URL (loops resolve it once)
+    pub fn with_base(base: &str) -> Self {
+        Self { url: base.into(), has_query: false }
+    }
+
+    // Site path or href, already encoded ("/Manga/A/AB/Title", "/reader/...?path=...")
+    pub fn path(mut self, path: &str) -> Self {
+        if !path.starts_with('/') { self.url.push('/'); }
+        self.url.push_str(path);
+        self.has_query = path.contains('?');
+        self
+    }
+
+    // Decoded file path ("/Manga/A/AB/Title/Title v01.zip"), encoded like the site's hrefs
+    pub fn file_path(self, path: &str) -> Self {
+        self.path(&encode_path(path))
+    }
+
+    // One decoded path segment, encoded strictly ("/tags" + "Slice of Life" -> "/tags/Slice%20of%20Life")
+    pub fn segment(mut self, segment: &str) -> Self {
+        self.url.push('/');
+        self.url.push_str(&encode_component(segment));
+        self
+    }
+
+    // Raw query string from an href ("?page=2")
+    pub fn query_string(mut self, query: &str) -> Self {
+        self.url.push_str(query);
+        self.has_query = true;
+        self
+    }
+
+    pub fn query(self, name: &str, value: &str) -> Self {
+        let value = encode_component(value);
+        self.param(name, &value)
+    }
+
+    // Query value holding a path; '/' separators stay literal
+    pub fn query_path(self, name: &str, value: &str) -> Self {
+        let value = encode_query_path(value);
+        self.param(name, &value)
+    }
+
+    fn param(mut self, name: &str, encoded: &str) -> Self {
+        self.url.push(if self.has_query { '&' } else { '?' });
+        self.url.push_str(name);
+        self.url.push('=');
+        self.url.push_str(encoded);
+        self.has_query = true;
+        self
+    }
+
+    pub fn build(self) -> String {
+        self.url
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use aidoku_test::aidoku_test;
+
+    #[aidoku_test]
+    fn query_paths_keep_separators() {
+        assert_eq!(encode_query_path("vol01/001.png"), "vol01/001.png");
+        assert_eq!(encode_query_path("Vol 01/Page 001 (1).png"), "Vol%2001/Page%20001%20%281%29.png");
+        assert_eq!(encode_query_path("A&B/01+02.png"), "A%26B/01%2B02.png");
+        assert_eq!(encode_query_path("vol01/é.png"), "vol01/%C3%A9.png");
+    }
+
+    #[aidoku_test]
+    fn reader_image_urls_with_nested_entries() {
+        let url = MadokamiUrl::with_base("https://manga.madokami.al")
+            .path("/reader/image")
+            .query_path("path", "/Manga/A/Title/Title v01.zip")
+            .query_path("file", "vol01/001.png")
+            .build();
+        assert_eq!(url, "https://manga.madokami.al/reader/image?path=/Manga/A/Title/Title%20v01.zip&file=vol01/001.png");
+    }
+
+    #[aidoku_test]
+    fn file_paths_keep_sub_delimiters() {
+        assert_eq!(encode_path("/Manga/A/Title (2019)/Title!, v01.zip"), "/Manga/A/Title%20(2019)/Title!,%20v01.zip");
+    }
+}